@@ -0,0 +1,72 @@
+// Axis-aligned bounding boxes
+
+use crate::vec3::{Point3, Vec3};
+
+#[derive(Debug, Clone, Copy)]
+pub struct Aabb {
+    pub min: Point3,
+    pub max: Point3,
+}
+
+impl Aabb {
+    pub fn new(min: Point3, max: Point3) -> Self {
+        Aabb { min, max }
+    }
+
+    // Contains nothing; the identity for `union`
+    pub fn empty() -> Self {
+        Aabb {
+            min: Point3::new(f64::INFINITY, f64::INFINITY, f64::INFINITY),
+            max: Point3::new(f64::NEG_INFINITY, f64::NEG_INFINITY, f64::NEG_INFINITY),
+        }
+    }
+
+    pub fn from_points(points: &[Point3]) -> Self {
+        points.iter().fold(Aabb::empty(), |bounds, &p| bounds.grow(p))
+    }
+
+    pub fn grow(&self, p: Point3) -> Aabb {
+        Aabb::new(self.min.min(&p), self.max.max(&p))
+    }
+
+    pub fn union(&self, other: &Aabb) -> Aabb {
+        self.grow(other.min).grow(other.max)
+    }
+
+    // Overlap of the two boxes; a degenerate box if they do not overlap
+    pub fn intersection(&self, other: &Aabb) -> Aabb {
+        let min = self.min.max(&other.min);
+        Aabb::new(min, self.max.min(&other.max).max(&min))
+    }
+
+    pub fn centroid(&self) -> Point3 {
+        self.min.lerp(&self.max, 0.5)
+    }
+
+    pub fn surface_area(&self) -> f64 {
+        let d = self.max - self.min;
+        if d.x < 0.0 || d.y < 0.0 || d.z < 0.0 {
+            return 0.0;
+        }
+        2.0 * (d.x * d.y + d.y * d.z + d.z * d.x)
+    }
+
+    // Slab test. Takes the reciprocal of the ray direction so callers can
+    // compute it once per ray. Returns the entry distance on a hit.
+    pub fn hit(&self, origin: Point3, inv_direction: Vec3, t_min: f64, t_max: f64) -> Option<f64> {
+        let tx0 = (self.min.x - origin.x) * inv_direction.x;
+        let tx1 = (self.max.x - origin.x) * inv_direction.x;
+        let ty0 = (self.min.y - origin.y) * inv_direction.y;
+        let ty1 = (self.max.y - origin.y) * inv_direction.y;
+        let tz0 = (self.min.z - origin.z) * inv_direction.z;
+        let tz1 = (self.max.z - origin.z) * inv_direction.z;
+
+        let t_near = t_min.max(tx0.min(tx1)).max(ty0.min(ty1)).max(tz0.min(tz1));
+        let t_far = t_max.min(tx0.max(tx1)).min(ty0.max(ty1)).min(tz0.max(tz1));
+        if t_near <= t_far {
+            Some(t_near)
+        } else {
+            None
+        }
+    }
+}
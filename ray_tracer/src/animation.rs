@@ -0,0 +1,194 @@
+// Keyframed values for animation
+//
+// A track holds a list of numbers at keyframes and interpolates between
+// them. Each key picks the curve used on the way to the next key, so one
+// track can hold still, ease in and move linearly in different stretches.
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Curve {
+    // Holds the key's values until the next key
+    Step,
+    Linear,
+    // Starts slowly, arrives at full speed
+    EaseIn,
+    // Starts at full speed, slows into the next key
+    EaseOut,
+    // Slow at both ends
+    Ease,
+    // Catmull-Rom spline through the neighbouring keys, for smooth paths
+    // such as camera fly-throughs
+    Smooth,
+}
+
+impl Curve {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "step" => Some(Curve::Step),
+            "linear" => Some(Curve::Linear),
+            "ease_in" => Some(Curve::EaseIn),
+            "ease_out" => Some(Curve::EaseOut),
+            "ease" => Some(Curve::Ease),
+            "smooth" => Some(Curve::Smooth),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Key {
+    pub frame: f64,
+    pub values: Vec<f64>,
+    // How the values move from this key to the next
+    pub curve: Curve,
+}
+
+#[derive(Debug, Clone)]
+pub struct Track {
+    // Sorted by frame
+    keys: Vec<Key>,
+}
+
+impl Track {
+    // Needs at least one key, and every key must have the same number of
+    // values
+    pub fn new(mut keys: Vec<Key>) -> Self {
+        assert!(!keys.is_empty(), "a track needs at least one key");
+        assert!(
+            keys.iter().all(|key| key.values.len() == keys[0].values.len()),
+            "every key of a track needs the same number of values"
+        );
+        keys.sort_by(|a, b| a.frame.total_cmp(&b.frame));
+        Track { keys }
+    }
+
+    // Values at `frame`. Before the first key and after the last the
+    // values hold.
+    pub fn sample(&self, frame: f64) -> Vec<f64> {
+        let keys = &self.keys;
+        let next = keys.partition_point(|key| key.frame <= frame);
+        if next == 0 {
+            return keys[0].values.clone();
+        }
+        if next == keys.len() {
+            return keys[next - 1].values.clone();
+        }
+
+        let (k0, k1) = (&keys[next - 1], &keys[next]);
+        let span = k1.frame - k0.frame;
+        let t = (frame - k0.frame) / span;
+        let eased = match k0.curve {
+            Curve::Step => 0.0,
+            Curve::Linear | Curve::Smooth => t,
+            Curve::EaseIn => t * t,
+            Curve::EaseOut => 1.0 - (1.0 - t) * (1.0 - t),
+            Curve::Ease => t * t * (3.0 - 2.0 * t),
+        };
+        if k0.curve != Curve::Smooth {
+            return (0..k0.values.len())
+                .map(|i| k0.values[i] + (k1.values[i] - k0.values[i]) * eased)
+                .collect();
+        }
+
+        // Cubic Hermite segment with Catmull-Rom slopes, which stays
+        // correct when keys are unevenly spaced
+        let (t2, t3) = (t * t, t * t * t);
+        let h00 = 2.0 * t3 - 3.0 * t2 + 1.0;
+        let h10 = t3 - 2.0 * t2 + t;
+        let h01 = -2.0 * t3 + 3.0 * t2;
+        let h11 = t3 - t2;
+        (0..k0.values.len())
+            .map(|i| {
+                let m0 = self.slope(next - 1, i) * span;
+                let m1 = self.slope(next, i) * span;
+                h00 * k0.values[i] + h10 * m0 + h01 * k1.values[i] + h11 * m1
+            })
+            .collect()
+    }
+
+    // Rate of change of value `i` at key `k`, from its neighbours
+    fn slope(&self, k: usize, i: usize) -> f64 {
+        let before = &self.keys[k.saturating_sub(1)];
+        let after = &self.keys[(k + 1).min(self.keys.len() - 1)];
+        if after.frame == before.frame {
+            return 0.0;
+        }
+        (after.values[i] - before.values[i]) / (after.frame - before.frame)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(frame: f64, value: f64, curve: Curve) -> Key {
+        Key {
+            frame,
+            values: vec![value, -value],
+            curve,
+        }
+    }
+
+    // One stretch from 0 at frame 10 to 8 at frame 20
+    fn stretch(curve: Curve) -> Track {
+        Track::new(vec![key(10.0, 0.0, curve), key(20.0, 8.0, curve)])
+    }
+
+    fn assert_close(a: f64, b: f64) {
+        assert!((a - b).abs() < 1e-9, "{} != {}", a, b);
+    }
+
+    #[test]
+    fn holds_outside_the_keys_and_hits_them_exactly() {
+        for curve in [Curve::Step, Curve::Linear, Curve::EaseIn, Curve::EaseOut, Curve::Ease, Curve::Smooth] {
+            let track = stretch(curve);
+            assert_eq!(track.sample(0.0), vec![0.0, 0.0]);
+            assert_eq!(track.sample(10.0), vec![0.0, 0.0]);
+            assert_eq!(track.sample(20.0), vec![8.0, -8.0]);
+            assert_eq!(track.sample(30.0), vec![8.0, -8.0]);
+        }
+    }
+
+    #[test]
+    fn curves_between_keys() {
+        // A quarter of the way through the stretch
+        let at = |curve| stretch(curve).sample(12.5)[0];
+        assert_eq!(at(Curve::Step), 0.0);
+        assert_close(at(Curve::Linear), 2.0);
+        assert_close(at(Curve::EaseIn), 0.5);
+        assert_close(at(Curve::EaseOut), 3.5);
+        assert_close(at(Curve::Ease), 8.0 * 0.15625);
+        // Every value of a key moves
+        assert_close(stretch(Curve::Linear).sample(15.0)[1], -4.0);
+    }
+
+    #[test]
+    fn each_key_picks_the_curve_to_the_next() {
+        let track = Track::new(vec![
+            key(0.0, 0.0, Curve::Step),
+            key(10.0, 1.0, Curve::Linear),
+            key(20.0, 3.0, Curve::Step),
+        ]);
+        assert_eq!(track.sample(9.9)[0], 0.0);
+        assert_close(track.sample(15.0)[0], 2.0);
+    }
+
+    #[test]
+    fn smooth_tracks_follow_straight_lines_through_uneven_keys() {
+        // Keys on a line stay on it, however far apart they are
+        let track = Track::new(vec![
+            key(3.0, 6.0, Curve::Smooth),
+            key(0.0, 0.0, Curve::Smooth),
+            key(1.0, 2.0, Curve::Smooth),
+            key(7.0, 14.0, Curve::Smooth),
+        ]);
+        for frame in [0.5, 1.0, 2.2, 5.0, 6.9] {
+            assert_close(track.sample(frame)[0], 2.0 * frame);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "at least one key")]
+    fn needs_a_key() {
+        Track::new(Vec::new());
+    }
+}
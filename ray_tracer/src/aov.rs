@@ -0,0 +1,231 @@
+// Arbitrary output variables (AOVs): extra images next to the beauty image
+//
+// Each AOV records something about the first surface seen through a pixel,
+// for compositing, relighting and denoising: how far away it is, which way
+// it faces, its unlit color, which object and material it belongs to and
+// how much of its light is shadowed. They are traced with their own random
+// numbers, so asking for them leaves the beauty image unchanged.
+
+use crate::color::Color;
+use crate::image::Image;
+use crate::rng::Rng;
+use crate::vec3::Vec3;
+use crate::{Ray, Scene};
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Aov {
+    // Distance from the camera along the ray; 0 where nothing is hit
+    Depth,
+    // World-space shading normal, facing the camera
+    Normal,
+    // Surface color without any lighting; the background where nothing
+    // is hit
+    Albedo,
+    // A flat color per object and per material
+    ObjectId,
+    MaterialId,
+    // Fraction of the light reaching a surface that is blocked, from 0
+    // (fully lit) to 1 (in shadow)
+    Shadow,
+}
+
+impl Aov {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "depth" => Some(Aov::Depth),
+            "normal" => Some(Aov::Normal),
+            "albedo" => Some(Aov::Albedo),
+            "object_id" => Some(Aov::ObjectId),
+            "material_id" => Some(Aov::MaterialId),
+            "shadow" => Some(Aov::Shadow),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Aov::Depth => "depth",
+            Aov::Normal => "normal",
+            Aov::Albedo => "albedo",
+            Aov::ObjectId => "object_id",
+            Aov::MaterialId => "material_id",
+            Aov::Shadow => "shadow",
+        }
+    }
+
+    // File the AOV is saved to, next to the beauty image: `render.png`
+    // gives `render.depth.png`
+    pub fn output(self, beauty: &Path) -> PathBuf {
+        let stem = beauty.file_stem().map(|stem| stem.to_string_lossy().into_owned()).unwrap_or_default();
+        let name = match beauty.extension() {
+            Some(extension) => format!("{}.{}.{}", stem, self.name(), extension.to_string_lossy()),
+            None => format!("{}.{}", stem, self.name()),
+        };
+        beauty.with_file_name(name)
+    }
+
+    // Maps the raw values into [0, 1] for integer image formats. A depth d
+    // becomes d / (d + m), with m the median depth in view, which keeps
+    // detail up close even when a ground plane stretches out almost
+    // endlessly; normals are moved from [-1, 1]. The other AOVs already
+    // fit.
+    pub fn display(self, image: &Image) -> Image {
+        let pixels = match self {
+            Aov::Depth => {
+                let mut depths: Vec<f64> = image.pixels.iter().map(|color| color.r).filter(|&d| d > 0.0).collect();
+                depths.sort_by(f64::total_cmp);
+                let median = depths.get(depths.len() / 2).copied().unwrap_or(1.0);
+                image.pixels.iter().map(|&color| color.map(|d| d / (d + median))).collect()
+            }
+            Aov::Normal => image.pixels.iter().map(|&color| color.map(|x| x * 0.5 + 0.5)).collect(),
+            _ => image.pixels.clone(),
+        };
+        Image {
+            width: image.width,
+            height: image.height,
+            pixels,
+        }
+    }
+}
+
+// What a single camera ray sees first
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct AovSample {
+    depth: f64,
+    normal: Vec3,
+    albedo: Color,
+    shadow: f64,
+    // Position of the object in the scene and of the material among the
+    // named ones, so both stay the same across frames
+    object: Option<usize>,
+    material: Option<usize>,
+}
+
+// Traces the AOVs of one camera ray. Shadow rays are only sent if
+// `shadow` is set.
+pub(crate) fn sample(scene: &Scene, ray: &Ray, shadow: bool, rng: &mut Rng) -> AovSample {
+    let Some((object, hit)) = scene.hit_object(ray, 0.0, f64::INFINITY) else {
+        return AovSample {
+            depth: 0.0,
+            normal: Vec3::zero(),
+            albedo: scene.background.color(ray.direction),
+            shadow: 0.0,
+            object: None,
+            material: None,
+        };
+    };
+    AovSample {
+        depth: hit.t,
+        normal: hit.normal,
+        albedo: hit.material.albedo(&hit),
+        shadow: if shadow { scene.shadow(&hit, ray.time, rng) } else { 0.0 },
+        object: Some(object),
+        material: Some(scene.material_id(hit.material)),
+    }
+}
+
+// Filter-weighted sums of the AOV samples in one pixel. The weights are
+// the beauty image's.
+#[derive(Debug, Clone, Copy)]
+pub struct AovPixel {
+    depth: f64,
+    normal: Vec3,
+    albedo: Color,
+    shadow: f64,
+    // IDs cannot be averaged, so the sample with the largest filter
+    // weight, nearest the pixel center, picks them
+    nearest: f64,
+    object: Option<usize>,
+    material: Option<usize>,
+}
+
+impl Default for AovPixel {
+    fn default() -> Self {
+        AovPixel {
+            depth: 0.0,
+            normal: Vec3::zero(),
+            albedo: Color::black(),
+            shadow: 0.0,
+            nearest: f64::NEG_INFINITY,
+            object: None,
+            material: None,
+        }
+    }
+}
+
+impl AovPixel {
+    pub(crate) fn add(&mut self, sample: &AovSample, weight: f64) {
+        self.depth += sample.depth * weight;
+        self.normal += sample.normal * weight;
+        self.albedo += sample.albedo * weight;
+        self.shadow += sample.shadow * weight;
+        if weight > self.nearest {
+            self.nearest = weight;
+            self.object = sample.object;
+            self.material = sample.material;
+        }
+    }
+
+    pub(crate) fn merge(&mut self, other: &AovPixel) {
+        self.depth += other.depth;
+        self.normal += other.normal;
+        self.albedo += other.albedo;
+        self.shadow += other.shadow;
+        if other.nearest > self.nearest {
+            self.nearest = other.nearest;
+            self.object = other.object;
+            self.material = other.material;
+        }
+    }
+}
+
+// Image of one AOV from its pixel sums and the sums of their weights
+pub(crate) fn resolve(aov: Aov, width: usize, height: usize, pixels: &[AovPixel], weights: &[f64]) -> Image {
+    let pixels = pixels
+        .iter()
+        .zip(weights)
+        .map(|(pixel, &weight)| {
+            let scale = if weight.abs() < 1e-12 { 0.0 } else { 1.0 / weight };
+            match aov {
+                Aov::Depth => gray(pixel.depth * scale),
+                Aov::Normal => {
+                    // Normals averaged across an edge are shorter than 1
+                    let normal = pixel.normal * scale;
+                    let normal = if normal.length() > 0.0 { normal.normalize() } else { normal };
+                    Color::new(normal.x, normal.y, normal.z)
+                }
+                Aov::Albedo => pixel.albedo * scale,
+                Aov::Shadow => gray(pixel.shadow * scale),
+                Aov::ObjectId => pixel.object.map_or(Color::black(), id_color),
+                Aov::MaterialId => pixel.material.map_or(Color::black(), id_color),
+            }
+        })
+        .collect();
+    Image { width, height, pixels }
+}
+
+fn gray(value: f64) -> Color {
+    Color::new(value, value, value)
+}
+
+// Bright, well separated color for ID `id`: hues step around the color
+// wheel by the golden ratio, so neighbouring IDs never look alike
+fn id_color(id: usize) -> Color {
+    let hue = (id as f64 * 0.618_033_988_75).fract() * 6.0;
+    let (saturation, value) = (0.75, 1.0);
+    let f = hue.fract();
+    let (p, q, t) = (
+        value * (1.0 - saturation),
+        value * (1.0 - saturation * f),
+        value * (1.0 - saturation * (1.0 - f)),
+    );
+    match hue as u32 {
+        0 => Color::new(value, t, p),
+        1 => Color::new(q, value, p),
+        2 => Color::new(p, value, t),
+        3 => Color::new(p, q, value),
+        4 => Color::new(t, p, value),
+        _ => Color::new(value, p, q),
+    }
+}
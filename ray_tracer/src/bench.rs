@@ -0,0 +1,115 @@
+// BVH vs. linear scan benchmark (`ray_tracer --bench`)
+//
+// Casts the same grid of rays into random triangle soups of increasing
+// size, once through a scene, whose BVH holds the triangles, and once by
+// testing every triangle, and checks that both find the same hits.
+
+use ray_tracer::environment::Background;
+use ray_tracer::hittable::{Hit, Hittable};
+use ray_tracer::material::Material;
+use ray_tracer::rng::Rng;
+use ray_tracer::shapes::Triangle;
+use ray_tracer::texture::Texture;
+use ray_tracer::{Color, Point3, Ray, Scene, Vec3};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+const SIZES: [usize; 4] = [100, 1_000, 10_000, 100_000];
+const RAY_GRID: usize = 64;
+
+pub fn run() {
+    println!(
+        "{:>10} {:>12} {:>16} {:>16} {:>9}",
+        "triangles", "build", "linear", "bvh", "speedup"
+    );
+
+    for &size in &SIZES {
+        let triangles = triangle_soup(size);
+        let rays = ray_grid();
+
+        let start = Instant::now();
+        let objects = triangle_soup(size)
+            .into_iter()
+            .map(|triangle| Box::new(triangle) as Box<dyn Hittable>)
+            .collect();
+        let scene = Scene::new(objects, Vec::new(), 0.0, Background::Solid(Color::black()));
+        let build_time = start.elapsed();
+
+        let (linear_time, linear_hits) = time(&rays, |ray| {
+            let mut closest: Option<Hit> = None;
+            for triangle in &triangles {
+                let limit = closest.map_or(f64::INFINITY, |hit| hit.t);
+                if let Some(hit) = triangle.intersect(ray, 0.0, limit) {
+                    closest = Some(hit);
+                }
+            }
+            closest
+        });
+        let (bvh_time, bvh_hits) = time(&rays, |ray| scene.hit(ray, 0.0, f64::INFINITY));
+
+        let mismatches = linear_hits
+            .iter()
+            .zip(&bvh_hits)
+            .filter(|(a, b)| match (a, b) {
+                (Some(a), Some(b)) => (a.t - b.t).abs() > 1e-9,
+                (None, None) => false,
+                _ => true,
+            })
+            .count();
+
+        println!(
+            "{:>10} {:>9.2} ms {:>16} {:>16} {:>8.1}x",
+            size,
+            build_time.as_secs_f64() * 1000.0,
+            rays_per_second(rays.len(), linear_time),
+            rays_per_second(rays.len(), bvh_time),
+            linear_time.as_secs_f64() / bvh_time.as_secs_f64()
+        );
+        if mismatches > 0 {
+            println!("           warning: {} rays disagree between linear scan and BVH", mismatches);
+        }
+    }
+}
+
+// Small random triangles scattered through a cube in front of the camera
+fn triangle_soup(count: usize) -> Vec<Triangle> {
+    let mut rng = Rng::new(count as u64);
+    let size = 10.0 / (count as f64).cbrt();
+    let material = Arc::new(Material::Lambertian {
+        albedo: Texture::Solid(Color::white()),
+    });
+    (0..count)
+        .map(|_| {
+            let center = Point3::new(rng.range(-5.0, 5.0), rng.range(-5.0, 5.0), rng.range(-15.0, -5.0));
+            let mut corner = || center + Vec3::new(rng.range(-size, size), rng.range(-size, size), rng.range(-size, size));
+            Triangle::new(corner(), corner(), corner(), material.clone())
+        })
+        .collect()
+}
+
+fn ray_grid() -> Vec<Ray> {
+    let mut rays = Vec::with_capacity(RAY_GRID * RAY_GRID);
+    for j in 0..RAY_GRID {
+        for i in 0..RAY_GRID {
+            let x = (i as f64 + 0.5) / RAY_GRID as f64 - 0.5;
+            let y = (j as f64 + 0.5) / RAY_GRID as f64 - 0.5;
+            rays.push(Ray::new(Point3::origin(), Vec3::new(x, y, -1.0)));
+        }
+    }
+    rays
+}
+
+fn time<'a>(rays: &[Ray], mut cast: impl FnMut(&Ray) -> Option<Hit<'a>>) -> (Duration, Vec<Option<Hit<'a>>>) {
+    let start = Instant::now();
+    let hits = rays.iter().map(&mut cast).collect();
+    (start.elapsed(), hits)
+}
+
+fn rays_per_second(count: usize, elapsed: Duration) -> String {
+    let rate = count as f64 / elapsed.as_secs_f64();
+    if rate >= 1e6 {
+        format!("{:.2} Mrays/s", rate / 1e6)
+    } else {
+        format!("{:.1} krays/s", rate / 1e3)
+    }
+}
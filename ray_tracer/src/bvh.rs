@@ -0,0 +1,298 @@
+// Bounding volume hierarchy
+//
+// The tree only stores bounding boxes and item indices; callers supply a
+// closure that intersects a single item. This lets the scene (over whole
+// objects) and meshes (over their triangles) share one implementation.
+
+use crate::aabb::Aabb;
+use crate::vec3::{Point3, Vec3};
+use crate::Ray;
+
+// Number of centroid buckets tried per axis when searching for a split
+const SAH_BINS: usize = 16;
+// Relative cost of visiting an interior node vs. intersecting one item
+const TRAVERSAL_COST: f64 = 0.125;
+// Nodes this small always become leaves
+const MIN_LEAF_SIZE: usize = 2;
+// Nodes larger than this are split even if SAH prefers a leaf
+const MAX_LEAF_SIZE: usize = 8;
+
+#[derive(Debug, Clone, Copy)]
+enum NodeKind {
+    // Items `indices[first..first + count]`
+    Leaf { first: usize, count: usize },
+    // The left child always follows its parent directly
+    Interior { right: usize },
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Node {
+    bounds: Aabb,
+    kind: NodeKind,
+}
+
+pub struct Bvh {
+    nodes: Vec<Node>,
+    indices: Vec<usize>,
+}
+
+impl Bvh {
+    // Builds a tree over items with the given bounding boxes. Item `i` in
+    // traversal callbacks refers to `bounds[i]`.
+    pub fn build(bounds: &[Aabb]) -> Self {
+        let mut bvh = Bvh {
+            nodes: Vec::with_capacity(2 * bounds.len()),
+            indices: (0..bounds.len()).collect(),
+        };
+        if !bounds.is_empty() {
+            let centroids: Vec<Point3> = bounds.iter().map(|b| b.centroid()).collect();
+            bvh.build_node(bounds, &centroids, 0, bounds.len());
+        }
+        bvh
+    }
+
+    // Bounds of everything in the tree
+    pub fn bounds(&self) -> Option<Aabb> {
+        self.nodes.first().map(|node| node.bounds)
+    }
+
+    fn build_node(&mut self, bounds: &[Aabb], centroids: &[Point3], first: usize, count: usize) -> usize {
+        let items = &self.indices[first..first + count];
+        let node_bounds = items.iter().fold(Aabb::empty(), |b, &i| b.union(&bounds[i]));
+        let index = self.nodes.len();
+        self.nodes.push(Node {
+            bounds: node_bounds,
+            kind: NodeKind::Leaf { first, count },
+        });
+        if count <= MIN_LEAF_SIZE {
+            return index;
+        }
+
+        let centroid_bounds = Aabb::from_points(&items.iter().map(|&i| centroids[i]).collect::<Vec<_>>());
+        let split = find_split(bounds, centroids, items, &centroid_bounds, node_bounds.surface_area());
+        let Some((axis, split_position, split_cost)) = split else {
+            // All centroids coincide; no split can separate them
+            return index;
+        };
+
+        let leaf_cost = count as f64;
+        if split_cost >= leaf_cost && count <= MAX_LEAF_SIZE {
+            return index;
+        }
+
+        // Partition the items around the chosen plane
+        let items = &mut self.indices[first..first + count];
+        let mut mid = 0;
+        for i in 0..items.len() {
+            if centroids[items[i]][axis] < split_position {
+                items.swap(i, mid);
+                mid += 1;
+            }
+        }
+        if mid == 0 || mid == count {
+            // Binning could not separate the items; fall back to a median split
+            items.sort_by(|&a, &b| centroids[a][axis].total_cmp(&centroids[b][axis]));
+            mid = count / 2;
+        }
+
+        self.build_node(bounds, centroids, first, mid);
+        let right = self.build_node(bounds, centroids, first + mid, count - mid);
+        self.nodes[index].kind = NodeKind::Interior { right };
+        index
+    }
+
+    // Finds the closest item hit. `intersect(item, t_max)` must return the
+    // hit distance along with whatever the caller wants back.
+    pub fn closest<T>(
+        &self,
+        ray: &Ray,
+        t_min: f64,
+        t_max: f64,
+        mut intersect: impl FnMut(usize, f64) -> Option<(f64, T)>,
+    ) -> Option<T> {
+        let mut closest = None;
+        self.traverse(ray, t_min, t_max, |item, limit| {
+            match intersect(item, limit) {
+                Some((t, hit)) => {
+                    closest = Some(hit);
+                    (t, false)
+                }
+                None => (limit, false),
+            }
+        });
+        closest
+    }
+
+    // Returns true as soon as any item reports a hit
+    pub fn any(&self, ray: &Ray, t_min: f64, t_max: f64, mut hits: impl FnMut(usize) -> bool) -> bool {
+        let mut found = false;
+        self.traverse(ray, t_min, t_max, |item, limit| {
+            found = hits(item);
+            (limit, found)
+        });
+        found
+    }
+
+    // Front-to-back traversal. The visitor returns the (possibly shrunk)
+    // search distance and whether to stop.
+    fn traverse(&self, ray: &Ray, t_min: f64, t_max: f64, mut visit: impl FnMut(usize, f64) -> (f64, bool)) {
+        if self.nodes.is_empty() {
+            return;
+        }
+        let inv_direction = Vec3::new(1.0 / ray.direction.x, 1.0 / ray.direction.y, 1.0 / ray.direction.z);
+        let mut t_max = t_max;
+        let mut stack = Vec::with_capacity(64);
+        stack.push(0);
+
+        while let Some(index) = stack.pop() {
+            let node = &self.nodes[index];
+            if node.bounds.hit(ray.origin, inv_direction, t_min, t_max).is_none() {
+                continue;
+            }
+            match node.kind {
+                NodeKind::Leaf { first, count } => {
+                    for &item in &self.indices[first..first + count] {
+                        let (limit, stop) = visit(item, t_max);
+                        if stop {
+                            return;
+                        }
+                        t_max = limit;
+                    }
+                }
+                NodeKind::Interior { right } => {
+                    let left = index + 1;
+                    let t_left = self.nodes[left].bounds.hit(ray.origin, inv_direction, t_min, t_max);
+                    let t_right = self.nodes[right].bounds.hit(ray.origin, inv_direction, t_min, t_max);
+                    // Push the farther child first so the nearer one is visited next
+                    match (t_left, t_right) {
+                        (Some(l), Some(r)) if l <= r => stack.extend([right, left]),
+                        (Some(_), Some(_)) => stack.extend([left, right]),
+                        (Some(_), None) => stack.push(left),
+                        (None, Some(_)) => stack.push(right),
+                        (None, None) => {}
+                    }
+                }
+            }
+        }
+    }
+}
+
+// Binned surface area heuristic. Returns the axis, the split position and
+// the estimated cost of splitting there, relative to one item test.
+fn find_split(
+    bounds: &[Aabb],
+    centroids: &[Point3],
+    items: &[usize],
+    centroid_bounds: &Aabb,
+    parent_area: f64,
+) -> Option<(usize, f64, f64)> {
+    let mut best: Option<(usize, f64, f64)> = None;
+
+    for axis in 0..3 {
+        let lo = centroid_bounds.min[axis];
+        let hi = centroid_bounds.max[axis];
+        if hi - lo <= 0.0 {
+            continue;
+        }
+
+        let mut bins = [(Aabb::empty(), 0usize); SAH_BINS];
+        let scale = SAH_BINS as f64 / (hi - lo);
+        for &i in items {
+            let centroid = centroids[i];
+            let bin = (((centroid[axis] - lo) * scale) as usize).min(SAH_BINS - 1);
+            bins[bin].0 = bins[bin].0.union(&bounds[i]);
+            bins[bin].1 += 1;
+        }
+
+        // Sweep from the right to get the area and count right of each plane
+        let mut right_area = [0.0; SAH_BINS];
+        let mut right_count = [0; SAH_BINS];
+        let (mut acc_bounds, mut acc_count) = (Aabb::empty(), 0);
+        for bin in (1..SAH_BINS).rev() {
+            acc_bounds = acc_bounds.union(&bins[bin].0);
+            acc_count += bins[bin].1;
+            right_area[bin] = acc_bounds.surface_area();
+            right_count[bin] = acc_count;
+        }
+
+        // Then from the left, evaluating the plane before each bin
+        let (mut acc_bounds, mut acc_count) = (Aabb::empty(), 0);
+        for bin in 1..SAH_BINS {
+            acc_bounds = acc_bounds.union(&bins[bin - 1].0);
+            acc_count += bins[bin - 1].1;
+            if acc_count == 0 || right_count[bin] == 0 {
+                continue;
+            }
+            let cost = TRAVERSAL_COST
+                + (acc_bounds.surface_area() * acc_count as f64 + right_area[bin] * right_count[bin] as f64)
+                    / parent_area.max(f64::MIN_POSITIVE);
+            if best.is_none_or(|(_, _, best_cost)| cost < best_cost) {
+                best = Some((axis, lo + bin as f64 / scale, cost));
+            }
+        }
+    }
+    best
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::Color;
+    use crate::hittable::Hittable;
+    use crate::material::Material;
+    use crate::rng::Rng;
+    use crate::shapes::{Sphere, Triangle};
+    use crate::texture::Texture;
+    use std::sync::Arc;
+
+    // Small spheres and triangles scattered through a cube around the
+    // origin
+    fn soup(rng: &mut Rng, count: usize) -> Vec<Box<dyn Hittable>> {
+        let material = Arc::new(Material::Lambertian {
+            albedo: Texture::Solid(Color::white()),
+        });
+        (0..count)
+            .map(|i| -> Box<dyn Hittable> {
+                let center = Point3::from(offset(rng, 5.0));
+                if i % 2 == 0 {
+                    Box::new(Sphere::new(center, rng.range(0.05, 0.5), material.clone()))
+                } else {
+                    let corners = [0; 3].map(|_| center + offset(rng, 0.5));
+                    Box::new(Triangle::new(corners[0], corners[1], corners[2], material.clone()))
+                }
+            })
+            .collect()
+    }
+
+    fn offset(rng: &mut Rng, size: f64) -> Vec3 {
+        Vec3::new(rng.range(-size, size), rng.range(-size, size), rng.range(-size, size))
+    }
+
+    #[test]
+    fn finds_the_same_closest_hit_as_a_linear_scan() {
+        let mut rng = Rng::new(7);
+        let objects = soup(&mut rng, 500);
+        let bounds: Vec<Aabb> = objects.iter().filter_map(|object| object.bounding_box()).collect();
+        let bvh = Bvh::build(&bounds);
+
+        let mut hits = 0;
+        for _ in 0..2000 {
+            let origin = Point3::from(offset(&mut rng, 8.0));
+            let ray = Ray::new(origin, rng.in_unit_sphere());
+
+            let mut linear = None;
+            for object in &objects {
+                if let Some(hit) = object.intersect(&ray, 1e-3, linear.unwrap_or(f64::INFINITY)) {
+                    linear = Some(hit.t);
+                }
+            }
+            let tree = bvh.closest(&ray, 1e-3, f64::INFINITY, |i, limit| {
+                objects[i].intersect(&ray, 1e-3, limit).map(|hit| (hit.t, hit.t))
+            });
+            assert_eq!(linear, tree, "ray {:?}", ray);
+            hits += linear.is_some() as usize;
+        }
+        // Make sure the rays did not all miss
+        assert!(hits > 100);
+    }
+}
@@ -0,0 +1,168 @@
+// Camera models
+//
+// The orthonormal basis and image plane are computed once when the camera
+// is built; `get_ray` only maps image coordinates to a ray.
+
+use crate::rng::Rng;
+use crate::vec3::{Point3, Vec3};
+use crate::Ray;
+use std::f64::consts::PI;
+
+#[derive(Debug, Clone, Copy)]
+pub enum Projection {
+    // Pinhole (or thin lens) with a vertical field of view in degrees
+    Perspective { fov: f64 },
+    // Parallel rays; `height` is the visible extent in world units
+    Orthographic { height: f64 },
+    // Equidistant circular fisheye; `fov` is the angle across the image
+    // circle, which touches the top and bottom edges of the frame
+    Fisheye { fov: f64 },
+    // Full 360° x 180° panorama (longitude across, latitude down); best
+    // rendered at a 2:1 aspect ratio
+    Equirectangular,
+}
+
+pub struct Camera {
+    position: Point3,
+    // Right, up and forward unit vectors
+    right: Vec3,
+    up: Vec3,
+    forward: Vec3,
+    projection: Projection,
+    aspect_ratio: f64,
+    // Image plane at unit distance in front of the camera (perspective) or
+    // through the camera position (orthographic)
+    lower_left: Point3,
+    horizontal: Vec3,
+    vertical: Vec3,
+    lens_radius: f64,
+    focus_distance: f64,
+    // Rays are sent at random times in [open, close]
+    shutter_open: f64,
+    shutter_close: f64,
+}
+
+impl Camera {
+    // Camera at `position` looking along `direction`
+    pub fn new(position: Point3, direction: Vec3, up: Vec3, projection: Projection, aspect_ratio: f64) -> Self {
+        let w = (-direction.normalize()).normalize();
+        let right = up.normalize().cross(&w).normalize();
+        let up = w.cross(&right);
+
+        let (half_width, half_height, distance) = match projection {
+            Projection::Perspective { fov } => {
+                let half_height = (fov.to_radians() / 2.0).tan();
+                (aspect_ratio * half_height, half_height, 1.0)
+            }
+            Projection::Orthographic { height } => (aspect_ratio * height / 2.0, height / 2.0, 0.0),
+            Projection::Fisheye { .. } | Projection::Equirectangular => (0.0, 0.0, 0.0),
+        };
+        let lower_left = position - right * half_width - up * half_height - w * distance;
+
+        Camera {
+            position,
+            right,
+            up,
+            forward: -w,
+            projection,
+            aspect_ratio,
+            lower_left,
+            horizontal: right * (2.0 * half_width),
+            vertical: up * (2.0 * half_height),
+            lens_radius: 0.0,
+            focus_distance: 1.0,
+            shutter_open: 0.0,
+            shutter_close: 0.0,
+        }
+    }
+
+    // Camera at `position` aimed at `target`
+    pub fn look_at(position: Point3, target: Point3, up: Vec3, projection: Projection, aspect_ratio: f64) -> Self {
+        Camera::new(position, target - position, up, projection, aspect_ratio)
+    }
+
+    // Thin lens depth of field: points `focus_distance` in front of the
+    // camera are sharp, and blur grows with the `aperture` diameter. Only
+    // perspective cameras have a lens.
+    pub fn with_lens(mut self, aperture: f64, focus_distance: f64) -> Self {
+        self.lens_radius = aperture / 2.0;
+        self.focus_distance = focus_distance;
+        self
+    }
+
+    // Moving objects are blurred along their path between the `open` and
+    // `close` times. An empty interval takes a still image at `open`.
+    pub fn with_shutter(mut self, open: f64, close: f64) -> Self {
+        self.shutter_open = open;
+        self.shutter_close = close.max(open);
+        self
+    }
+
+    // Ray through image coordinates (u, v), with (0, 0) at the bottom left
+    // and (1, 1) at the top right. `None` outside a fisheye's image circle.
+    pub fn get_ray(&self, u: f64, v: f64, rng: &mut Rng) -> Option<Ray> {
+        let ray = self.project(u, v, rng)?;
+        let time = if self.shutter_close > self.shutter_open {
+            rng.range(self.shutter_open, self.shutter_close)
+        } else {
+            self.shutter_open
+        };
+        Some(ray.with_time(time))
+    }
+
+    fn project(&self, u: f64, v: f64, rng: &mut Rng) -> Option<Ray> {
+        match self.projection {
+            Projection::Perspective { .. } => {
+                let direction = self.lower_left - self.position + self.horizontal * u + self.vertical * v;
+                if self.lens_radius <= 0.0 {
+                    return Some(Ray::new(self.position, direction));
+                }
+                // Every ray through the lens meets at the same point on the
+                // focus plane
+                let focus = self.position + direction * self.focus_distance;
+                let (dx, dy) = unit_disk(rng);
+                let origin = self.position
+                    + self.right * (dx * self.lens_radius)
+                    + self.up * (dy * self.lens_radius);
+                Some(Ray::new(origin, focus - origin))
+            }
+            Projection::Orthographic { .. } => {
+                let origin = self.lower_left + self.horizontal * u + self.vertical * v;
+                Some(Ray::new(origin, self.forward))
+            }
+            Projection::Fisheye { fov } => {
+                let x = (2.0 * u - 1.0) * self.aspect_ratio;
+                let y = 2.0 * v - 1.0;
+                let r = (x * x + y * y).sqrt();
+                if r > 1.0 {
+                    return None;
+                }
+                // Angle from the view axis grows linearly with the radius
+                let theta = r * fov.to_radians() / 2.0;
+                let phi = y.atan2(x);
+                let direction = self.forward * theta.cos()
+                    + self.right * (theta.sin() * phi.cos())
+                    + self.up * (theta.sin() * phi.sin());
+                Some(Ray::new(self.position, direction))
+            }
+            Projection::Equirectangular => {
+                let longitude = (u - 0.5) * 2.0 * PI;
+                let latitude = (v - 0.5) * PI;
+                let direction = self.forward * (latitude.cos() * longitude.cos())
+                    + self.right * (latitude.cos() * longitude.sin())
+                    + self.up * latitude.sin();
+                Some(Ray::new(self.position, direction))
+            }
+        }
+    }
+}
+
+// Uniform point on the unit disk
+fn unit_disk(rng: &mut Rng) -> (f64, f64) {
+    loop {
+        let (x, y) = (rng.range(-1.0, 1.0), rng.range(-1.0, 1.0));
+        if x * x + y * y < 1.0 {
+            return (x, y);
+        }
+    }
+}
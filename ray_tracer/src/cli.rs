@@ -0,0 +1,154 @@
+// Command line parsing
+
+use ray_tracer::aov::Aov;
+use ray_tracer::image_io::ImageFormat;
+use ray_tracer::tone_map::{Gamma, ToneMap};
+use std::path::PathBuf;
+
+pub const USAGE: &str = "\
+Usage: ray_tracer <scene-file> [<width> <height>] [options]
+       ray_tracer --bench
+
+Options:
+  -o, --output <path>     Output file (overrides the scene's `output`)
+  -f, --format <format>   ppm, ppm-binary, png, png16 or exr
+                          (default: guessed from the output extension)
+  -p, --passes <count>    Spread the samples over this many passes, saving
+                          the image after each
+  -e, --exposure <stops>  Brighten (positive) or darken (negative) the image
+  -t, --tone-map <curve>  clamp, reinhard or aces
+  -g, --gamma <encoding>  srgb (default) or a power curve exponent; 1 writes
+                          linear values
+  -r, --frames <range>    Render animation frames, as `<first>-<last>` or a
+                          single frame number
+  -a, --aovs <list>       Also save these passes next to the image, separated
+                          by commas: depth, normal, albedo, object_id,
+                          material_id, shadow
+  -d, --denoise           Filter the noise out of the final image";
+
+pub enum Command {
+    Render(Options),
+    Bench,
+}
+
+// Everything given here overrides the scene file's `settings` block
+pub struct Options {
+    pub scene: PathBuf,
+    pub size: Option<(usize, usize)>,
+    pub output: Option<PathBuf>,
+    pub format: Option<ImageFormat>,
+    pub passes: Option<u32>,
+    pub exposure: Option<f64>,
+    pub tone_map: Option<ToneMap>,
+    pub gamma: Option<Gamma>,
+    pub frames: Option<(u32, u32)>,
+    pub aovs: Option<Vec<Aov>>,
+    pub denoise: bool,
+}
+
+pub fn parse(args: &[String]) -> Result<Command, String> {
+    if args == ["--bench"] {
+        return Ok(Command::Bench);
+    }
+
+    let mut positional = Vec::new();
+    let mut output = None;
+    let mut format = None;
+    let mut passes = None;
+    let mut exposure = None;
+    let mut tone_map = None;
+    let mut gamma = None;
+    let mut frames = None;
+    let mut aovs = None;
+    let mut denoise = false;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .ok_or_else(|| format!("`{}` needs a value", arg))
+        };
+        match arg.as_str() {
+            "-o" | "--output" => output = Some(PathBuf::from(value()?)),
+            "-f" | "--format" => {
+                let name = value()?;
+                format = Some(ImageFormat::from_name(name).ok_or_else(|| format!("unknown format `{}`", name))?);
+            }
+            "-p" | "--passes" => {
+                let count = value()?;
+                passes = Some(
+                    count
+                        .parse::<u32>()
+                        .ok()
+                        .filter(|&count| count >= 1)
+                        .ok_or_else(|| format!("invalid pass count `{}`", count))?,
+                );
+            }
+            "-e" | "--exposure" => {
+                let stops = value()?;
+                exposure = Some(
+                    stops
+                        .parse::<f64>()
+                        .ok()
+                        .filter(|stops| stops.is_finite())
+                        .ok_or_else(|| format!("invalid exposure `{}`", stops))?,
+                );
+            }
+            "-t" | "--tone-map" => {
+                let name = value()?;
+                tone_map = Some(ToneMap::from_name(name).ok_or_else(|| format!("unknown tone map `{}`", name))?);
+            }
+            "-g" | "--gamma" => {
+                let name = value()?;
+                gamma = Some(Gamma::from_name(name).ok_or_else(|| format!("invalid gamma `{}`", name))?);
+            }
+            "-r" | "--frames" => {
+                let range = value()?;
+                frames = Some(frame_range(range).ok_or_else(|| format!("invalid frame range `{}`", range))?);
+            }
+            "-a" | "--aovs" => {
+                let list = value()?;
+                aovs = Some(
+                    list.split(',')
+                        .map(|name| Aov::from_name(name).ok_or_else(|| format!("unknown AOV `{}`", name)))
+                        .collect::<Result<Vec<_>, _>>()?,
+                );
+            }
+            "-d" | "--denoise" => denoise = true,
+            flag if flag.starts_with('-') && flag.len() > 1 => return Err(format!("unknown option `{}`", flag)),
+            _ => positional.push(arg),
+        }
+    }
+
+    let size = match positional.len() {
+        1 => None,
+        3 => Some((dimension(positional[1], "width")?, dimension(positional[2], "height")?)),
+        _ => return Err("expected a scene file and optionally a width and height".to_string()),
+    };
+    Ok(Command::Render(Options {
+        scene: PathBuf::from(positional[0]),
+        size,
+        output,
+        format,
+        passes,
+        exposure,
+        tone_map,
+        gamma,
+        frames,
+        aovs,
+        denoise,
+    }))
+}
+
+// `12` or `1-48`
+fn frame_range(arg: &str) -> Option<(u32, u32)> {
+    let (first, last) = arg.split_once('-').unwrap_or((arg, arg));
+    let (first, last) = (first.parse().ok()?, last.parse().ok()?);
+    (first <= last).then_some((first, last))
+}
+
+fn dimension(arg: &str, name: &str) -> Result<usize, String> {
+    match arg.parse() {
+        Ok(value) if value >= 2 => Ok(value),
+        _ => Err(format!("invalid {} `{}`", name, arg)),
+    }
+}
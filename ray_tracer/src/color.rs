@@ -0,0 +1,187 @@
+// Linear RGB colors
+//
+// Kept apart from `Vec3` so radiance and reflectance cannot be mixed up
+// with positions or directions. Products of colors are component-wise:
+// light of one color reflected by a surface of another.
+
+use std::iter::Sum;
+use std::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Sub, SubAssign};
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Color {
+    pub r: f64,
+    pub g: f64,
+    pub b: f64,
+}
+
+impl Color {
+    pub fn new(r: f64, g: f64, b: f64) -> Self {
+        Color { r, g, b }
+    }
+
+    pub fn black() -> Self {
+        Color::new(0.0, 0.0, 0.0)
+    }
+
+    pub fn white() -> Self {
+        Color::new(1.0, 1.0, 1.0)
+    }
+
+    // Mean of the three channels
+    pub fn average(&self) -> f64 {
+        (self.r + self.g + self.b) / 3.0
+    }
+
+    // Perceived brightness (Rec. 709 weights)
+    pub fn luminance(&self) -> f64 {
+        0.2126 * self.r + 0.7152 * self.g + 0.0722 * self.b
+    }
+
+    pub fn max_channel(&self) -> f64 {
+        self.r.max(self.g).max(self.b)
+    }
+
+    // Applies `f` to every channel
+    pub fn map(&self, f: impl Fn(f64) -> f64) -> Color {
+        Color::new(f(self.r), f(self.g), f(self.b))
+    }
+
+    // `self` at t = 0, `other` at t = 1
+    pub fn lerp(&self, other: &Color, t: f64) -> Color {
+        *self * (1.0 - t) + *other * t
+    }
+}
+
+impl Add for Color {
+    type Output = Color;
+
+    fn add(self, other: Color) -> Color {
+        Color::new(self.r + other.r, self.g + other.g, self.b + other.b)
+    }
+}
+
+impl Sub for Color {
+    type Output = Color;
+
+    fn sub(self, other: Color) -> Color {
+        Color::new(self.r - other.r, self.g - other.g, self.b - other.b)
+    }
+}
+
+impl Mul for Color {
+    type Output = Color;
+
+    fn mul(self, other: Color) -> Color {
+        Color::new(self.r * other.r, self.g * other.g, self.b * other.b)
+    }
+}
+
+impl Mul<f64> for Color {
+    type Output = Color;
+
+    fn mul(self, rhs: f64) -> Color {
+        Color::new(self.r * rhs, self.g * rhs, self.b * rhs)
+    }
+}
+
+impl Mul<Color> for f64 {
+    type Output = Color;
+
+    fn mul(self, rhs: Color) -> Color {
+        rhs * self
+    }
+}
+
+impl Div<f64> for Color {
+    type Output = Color;
+
+    fn div(self, rhs: f64) -> Color {
+        Color::new(self.r / rhs, self.g / rhs, self.b / rhs)
+    }
+}
+
+impl Div for Color {
+    type Output = Color;
+
+    fn div(self, other: Color) -> Color {
+        Color::new(self.r / other.r, self.g / other.g, self.b / other.b)
+    }
+}
+
+impl AddAssign for Color {
+    fn add_assign(&mut self, other: Color) {
+        *self = *self + other;
+    }
+}
+
+impl SubAssign for Color {
+    fn sub_assign(&mut self, other: Color) {
+        *self = *self - other;
+    }
+}
+
+impl MulAssign for Color {
+    fn mul_assign(&mut self, other: Color) {
+        *self = *self * other;
+    }
+}
+
+impl MulAssign<f64> for Color {
+    fn mul_assign(&mut self, rhs: f64) {
+        *self = *self * rhs;
+    }
+}
+
+impl DivAssign<f64> for Color {
+    fn div_assign(&mut self, rhs: f64) {
+        *self = *self / rhs;
+    }
+}
+
+impl DivAssign for Color {
+    fn div_assign(&mut self, other: Color) {
+        *self = *self / other;
+    }
+}
+
+impl Sum for Color {
+    fn sum<I: Iterator<Item = Color>>(iter: I) -> Color {
+        iter.fold(Color::black(), Add::add)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn arithmetic_is_per_channel() {
+        let (a, b) = (Color::new(0.5, 1.0, 2.0), Color::new(0.25, 0.5, 4.0));
+        assert_eq!(a + b, Color::new(0.75, 1.5, 6.0));
+        assert_eq!(a - b, Color::new(0.25, 0.5, -2.0));
+        assert_eq!(a * b, Color::new(0.125, 0.5, 8.0));
+        assert_eq!(a / b, Color::new(2.0, 2.0, 0.5));
+        assert_eq!(a * 2.0, Color::new(1.0, 2.0, 4.0));
+        assert_eq!(2.0 * a, a * 2.0);
+        assert_eq!(a / 2.0, Color::new(0.25, 0.5, 1.0));
+        assert_eq!([a, b].into_iter().sum::<Color>(), a + b);
+    }
+
+    #[test]
+    fn assignment_matches_the_binary_operators() {
+        let (a, b) = (Color::new(0.5, 1.0, 2.0), Color::new(0.25, 0.5, 4.0));
+        let mut c = a;
+        c += b;
+        assert_eq!(c, a + b);
+        c -= b;
+        assert_eq!(c, a);
+        c *= b;
+        assert_eq!(c, a * b);
+        c /= b;
+        assert_eq!(c, a);
+        c *= 4.0;
+        assert_eq!(c, a * 4.0);
+        c /= 4.0;
+        assert_eq!(c, a);
+    }
+}
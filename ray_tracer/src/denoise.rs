@@ -0,0 +1,227 @@
+// Denoising of low sample count renders
+//
+// An edge-avoiding à-trous filter (Dammertz et al. 2010): a 5x5 blur is
+// repeated with its taps spread further apart each time, so a few passes
+// cover a wide area. Every tap is weighted by how alike the two pixels'
+// normals, depths and colors are, so the blur stops at the edges of
+// objects and shadows. Surface color is divided out first and multiplied
+// back at the end, so textures stay sharp while their lighting is smoothed.
+
+use crate::color::Color;
+use crate::image::Image;
+use crate::vec3::Vec3;
+use rayon::prelude::*;
+
+// Each pass doubles the spacing of the taps, so 5 passes reach 2 * 16
+// pixels out
+const PASSES: u32 = 5;
+// B3 spline weights of the 5 taps along each axis
+const KERNEL: [f64; 5] = [1.0 / 16.0, 1.0 / 4.0, 3.0 / 8.0, 1.0 / 4.0, 1.0 / 16.0];
+// Sharpness of the normal test: the cosine between the normals is raised
+// to this power
+const NORMAL_POWER: i32 = 64;
+// Relative depth difference, per pixel of tap spacing, that cuts a tap's
+// weight to about a third
+const DEPTH_TOLERANCE: f64 = 0.02;
+// Relative brightness difference tolerated in the first pass, before
+// `strength`; it halves with every pass
+const COLOR_TOLERANCE: f64 = 1.0;
+// Albedo below this is clamped before dividing by it
+const MIN_ALBEDO: f64 = 0.01;
+
+// Buffers that describe the surfaces behind each pixel, from the `Normal`,
+// `Albedo` and `Depth` AOVs. Pixels with depth 0 saw no surface.
+pub struct Guides {
+    pub normal: Image,
+    pub albedo: Image,
+    pub depth: Image,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Denoiser {
+    // Scales how different two pixels' colors may be and still be
+    // blended: higher removes more noise but also more fine shading
+    pub strength: f64,
+}
+
+impl Default for Denoiser {
+    fn default() -> Self {
+        Denoiser { strength: 1.0 }
+    }
+}
+
+impl Denoiser {
+    pub fn apply(&self, image: &Image, guides: &Guides) -> Image {
+        let (width, height) = (image.width, image.height);
+        let albedo: Vec<Color> = guides.albedo.pixels.iter().map(|a| a.map(|x| x.max(MIN_ALBEDO))).collect();
+        let surfaces = Surfaces {
+            width,
+            height,
+            normals: guides.normal.pixels.iter().map(|n| Vec3::new(n.r, n.g, n.b)).collect(),
+            depths: guides.depth.pixels.iter().map(|d| d.r).collect(),
+        };
+
+        // Lighting without the surface color
+        let mut light: Vec<Color> = image.pixels.iter().zip(&albedo).map(|(&color, &a)| color / a).collect();
+        for pass in 0..PASSES {
+            let step = 1 << pass;
+            let tolerance = self.strength * COLOR_TOLERANCE / step as f64;
+            let (source, surfaces) = (&light, &surfaces);
+            light = (0..height)
+                .into_par_iter()
+                .flat_map_iter(|y| (0..width).map(move |x| surfaces.filter(source, x, y, step, tolerance)))
+                .collect();
+        }
+
+        Image {
+            width,
+            height,
+            pixels: light.iter().zip(&albedo).map(|(&light, &a)| light * a).collect(),
+        }
+    }
+}
+
+// The guide buffers, row by row
+struct Surfaces {
+    width: usize,
+    height: usize,
+    normals: Vec<Vec3>,
+    depths: Vec<f64>,
+}
+
+impl Surfaces {
+    // One à-trous tap pattern around pixel (x, y), with taps `step`
+    // pixels apart. Background pixels are left alone and never blended
+    // into surfaces.
+    fn filter(&self, light: &[Color], x: usize, y: usize, step: usize, tolerance: f64) -> Color {
+        let p = y * self.width + x;
+        if self.depths[p] <= 0.0 {
+            return light[p];
+        }
+        let brightness = light[p].luminance();
+
+        let mut sum = Color::black();
+        let mut total = 0.0;
+        for (j, ky) in KERNEL.iter().enumerate() {
+            let Some(qy) = offset(y, j, step, self.height) else {
+                continue;
+            };
+            for (i, kx) in KERNEL.iter().enumerate() {
+                let Some(qx) = offset(x, i, step, self.width) else {
+                    continue;
+                };
+                let q = qy * self.width + qx;
+                if self.depths[q] <= 0.0 {
+                    continue;
+                }
+                let normal_weight = self.normals[p].dot(&self.normals[q]).max(0.0).powi(NORMAL_POWER);
+                let depth_difference = (self.depths[p] - self.depths[q]).abs();
+                let depth_weight = (-depth_difference / (DEPTH_TOLERANCE * step as f64 * self.depths[p])).exp();
+                let difference = (brightness - light[q].luminance()).abs();
+                let scale = 0.5 * (brightness + light[q].luminance()) + 1e-4;
+                let color_weight = (-difference / (tolerance * scale)).exp();
+
+                let weight = kx * ky * normal_weight * depth_weight * color_weight;
+                sum += light[q] * weight;
+                total += weight;
+            }
+        }
+        if total > 0.0 {
+            sum / total
+        } else {
+            light[p]
+        }
+    }
+}
+
+// Coordinate of tap `tap` (0 to 4, 2 being the center) along an axis of
+// `size` pixels, if it is inside the image
+fn offset(center: usize, tap: usize, step: usize, size: usize) -> Option<usize> {
+    let coordinate = center as isize + (tap as isize - 2) * step as isize;
+    (0..size as isize).contains(&coordinate).then_some(coordinate as usize)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::Rng;
+
+    const SIZE: usize = 32;
+
+    fn image(mut pixel: impl FnMut(usize, usize) -> Color) -> Image {
+        Image {
+            width: SIZE,
+            height: SIZE,
+            pixels: (0..SIZE * SIZE).map(|i| pixel(i % SIZE, i / SIZE)).collect(),
+        }
+    }
+
+    // A wall facing the camera at depth 2, with a checkered albedo
+    fn wall() -> Guides {
+        Guides {
+            normal: image(|_, _| Color::new(0.0, 0.0, 1.0)),
+            albedo: image(|x, y| if (x / 4 + y / 4) % 2 == 0 { Color::new(0.8, 0.2, 0.2) } else { Color::white() }),
+            depth: image(|_, _| Color::new(2.0, 2.0, 2.0)),
+        }
+    }
+
+    fn mean_and_variance(pixels: &[Color]) -> (f64, f64) {
+        let n = pixels.len() as f64;
+        let mean = pixels.iter().map(|c| c.luminance()).sum::<f64>() / n;
+        let variance = pixels.iter().map(|c| (c.luminance() - mean).powi(2)).sum::<f64>() / n;
+        (mean, variance)
+    }
+
+    #[test]
+    fn evenly_lit_images_are_unchanged() {
+        // Texture detail is divided out, so it survives untouched
+        let guides = wall();
+        let lit = image(|x, y| guides.albedo.get(x, y) * 0.6);
+        let denoised = Denoiser::default().apply(&lit, &guides);
+        for (a, b) in denoised.pixels.iter().zip(&lit.pixels) {
+            assert!((a.r - b.r).abs() + (a.g - b.g).abs() + (a.b - b.b).abs() < 1e-9, "{:?} != {:?}", a, b);
+        }
+    }
+
+    #[test]
+    fn reduces_noise_and_keeps_the_mean() {
+        let guides = Guides {
+            albedo: image(|_, _| Color::white()),
+            ..wall()
+        };
+        let mut rng = Rng::new(1);
+        let noisy = image(|_, _| {
+            let x = rng.range(0.3, 0.7);
+            Color::new(x, x, x)
+        });
+        let denoised = Denoiser::default().apply(&noisy, &guides);
+
+        let (noisy_mean, noisy_variance) = mean_and_variance(&noisy.pixels);
+        let (mean, variance) = mean_and_variance(&denoised.pixels);
+        assert!((mean - noisy_mean).abs() < 0.01, "{} != {}", mean, noisy_mean);
+        assert!(variance < noisy_variance / 10.0, "{} from {}", variance, noisy_variance);
+    }
+
+    #[test]
+    fn stops_at_edges_and_leaves_the_background() {
+        // Two walls meeting at a right angle down the middle, one bright
+        // and one dark, with sky above
+        let guides = Guides {
+            normal: image(|x, _| if x < SIZE / 2 { Color::new(0.0, 0.0, 1.0) } else { Color::new(1.0, 0.0, 0.0) }),
+            albedo: image(|_, _| Color::white()),
+            depth: image(|_, y| if y < 4 { Color::black() } else { Color::new(2.0, 2.0, 2.0) }),
+        };
+        let lit = image(|x, y| match (x < SIZE / 2, y < 4) {
+            (_, true) => Color::new(0.3, 0.5, 0.9),
+            (true, false) => Color::new(0.9, 0.9, 0.9),
+            (false, false) => Color::new(0.1, 0.1, 0.1),
+        });
+        let denoised = Denoiser { strength: 4.0 }.apply(&lit, &guides);
+        for y in 0..SIZE {
+            for x in 0..SIZE {
+                let (a, b) = (denoised.get(x, y), lit.get(x, y));
+                assert!((a.luminance() - b.luminance()).abs() < 1e-6, "({}, {}): {:?} != {:?}", x, y, a, b);
+            }
+        }
+    }
+}
@@ -0,0 +1,216 @@
+// What rays see when they miss every object
+//
+// Environment maps are equirectangular images wrapped around the scene:
+// longitude runs across the image with -z in the middle, latitude from
+// straight up (top row) to straight down. They also light the scene, with
+// directions importance sampled by brightness.
+
+use crate::color::Color;
+use crate::image::Image;
+use crate::rng::Rng;
+use crate::vec3::Vec3;
+use std::f64::consts::PI;
+use std::sync::Arc;
+
+#[derive(Debug, Clone)]
+pub enum Background {
+    Solid(Color),
+    // Vertical blend from `bottom` (looking straight down) to `top`
+    Gradient { bottom: Color, top: Color },
+    Environment(Arc<EnvironmentMap>),
+}
+
+impl Background {
+    pub fn color(&self, direction: Vec3) -> Color {
+        match self {
+            Background::Solid(color) => *color,
+            Background::Gradient { bottom, top } => {
+                let t = 0.5 * (direction.y + 1.0);
+                bottom.lerp(top, t)
+            }
+            Background::Environment(map) => map.radiance(direction),
+        }
+    }
+
+    // Whether the background is also one of the scene's lights, so paths
+    // that already sampled it directly must not count it again on a miss
+    pub fn is_light(&self) -> bool {
+        matches!(self, Background::Environment(_))
+    }
+}
+
+#[derive(Debug)]
+pub struct EnvironmentMap {
+    image: Image,
+    intensity: f64,
+    // Turn around the vertical axis, in radians
+    rotation: f64,
+    // Cumulative distributions for picking a row, then a column within it,
+    // in proportion to brightness times the solid angle a pixel covers
+    row_cdf: Vec<f64>,
+    column_cdfs: Vec<Vec<f64>>,
+}
+
+impl EnvironmentMap {
+    pub fn new(image: Image, intensity: f64, rotation_degrees: f64) -> Self {
+        let (width, height) = (image.width, image.height);
+        let mut column_cdfs = Vec::with_capacity(height);
+        let mut row_weights = Vec::with_capacity(height);
+        for y in 0..height {
+            let sin_theta = (PI * (y as f64 + 0.5) / height as f64).sin();
+            let weights: Vec<f64> = (0..width)
+                .map(|x| image.get(x, y).luminance().max(0.0) * sin_theta)
+                .collect();
+            row_weights.push(weights.iter().sum());
+            column_cdfs.push(cumulative(&weights));
+        }
+
+        EnvironmentMap {
+            row_cdf: cumulative(&row_weights),
+            column_cdfs,
+            image,
+            intensity,
+            rotation: rotation_degrees.to_radians(),
+        }
+    }
+
+    // Bilinearly filtered, wrapping around horizontally
+    pub fn radiance(&self, direction: Vec3) -> Color {
+        let (width, height) = (self.image.width as i64, self.image.height as i64);
+        let longitude = direction.x.atan2(-direction.z) - self.rotation;
+        let x = (longitude / (2.0 * PI) + 0.5) * width as f64 - 0.5;
+        let y = direction.y.clamp(-1.0, 1.0).acos() / PI * height as f64 - 0.5;
+        let (x0, y0) = (x.floor(), y.floor());
+        let (fx, fy) = (x - x0, y - y0);
+
+        let texel = |dx: i64, dy: i64| {
+            let tx = (x0 as i64 + dx).rem_euclid(width) as usize;
+            let ty = (y0 as i64 + dy).clamp(0, height - 1) as usize;
+            self.image.get(tx, ty)
+        };
+        let top = texel(0, 0) * (1.0 - fx) + texel(1, 0) * fx;
+        let bottom = texel(0, 1) * (1.0 - fx) + texel(1, 1) * fx;
+        (top * (1.0 - fy) + bottom * fy) * self.intensity
+    }
+
+    // Picks a direction with probability proportional to its brightness.
+    // Returns the direction, the radiance from it and its probability
+    // density per unit solid angle; `None` if the map is black.
+    pub fn sample(&self, rng: &mut Rng) -> Option<(Vec3, Color, f64)> {
+        let (y, row_pdf) = pick(&self.row_cdf, rng.next_f64())?;
+        let (x, column_pdf) = pick(&self.column_cdfs[y], rng.next_f64())?;
+
+        let u = (x as f64 + rng.next_f64()) / self.image.width as f64;
+        let v = (y as f64 + rng.next_f64()) / self.image.height as f64;
+        let theta = v * PI;
+        let longitude = (u - 0.5) * 2.0 * PI + self.rotation;
+        let direction = Vec3::new(
+            theta.sin() * longitude.sin(),
+            theta.cos(),
+            -theta.sin() * longitude.cos(),
+        );
+
+        // Density over the image's unit square, converted to solid angle
+        let pdf_uv = row_pdf * column_pdf * (self.image.width * self.image.height) as f64;
+        let sin_theta = theta.sin();
+        if sin_theta <= 0.0 {
+            return None;
+        }
+        let pdf = pdf_uv / (2.0 * PI * PI * sin_theta);
+        Some((direction, self.image.get(x, y) * self.intensity, pdf))
+    }
+}
+
+// Running totals of `weights`, with a leading zero
+fn cumulative(weights: &[f64]) -> Vec<f64> {
+    let mut cdf = Vec::with_capacity(weights.len() + 1);
+    let mut total = 0.0;
+    cdf.push(0.0);
+    for weight in weights {
+        total += weight;
+        cdf.push(total);
+    }
+    cdf
+}
+
+// Index whose CDF interval contains `r` (scaled to the total), and the
+// probability of picking it
+fn pick(cdf: &[f64], r: f64) -> Option<(usize, f64)> {
+    let total = *cdf.last()?;
+    if total <= 0.0 {
+        return None;
+    }
+    let target = r * total;
+    let index = cdf.partition_point(|&c| c <= target).clamp(1, cdf.len() - 1) - 1;
+    Some((index, (cdf[index + 1] - cdf[index]) / total))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Bright band above the horizon in one column, dim elsewhere
+    fn map() -> EnvironmentMap {
+        let (width, height) = (8, 4);
+        let pixels = (0..width * height)
+            .map(|i| match (i % width, i / width) {
+                (2, 1) => Color::new(8.0, 8.0, 8.0),
+                (_, 3) => Color::black(),
+                _ => Color::new(0.5, 0.5, 0.5),
+            })
+            .collect();
+        EnvironmentMap::new(Image { width, height, pixels }, 2.0, 30.0)
+    }
+
+    #[test]
+    fn samples_carry_the_radiance_they_are_picked_for() {
+        let map = map();
+        let mut rng = Rng::new(1);
+        for _ in 0..1000 {
+            let (direction, radiance, pdf) = map.sample(&mut rng).unwrap();
+            assert!((direction.length() - 1.0).abs() < 1e-9);
+            assert!(pdf > 0.0);
+            // Never picks a black pixel; the radiance reported is the
+            // unfiltered pixel, so only its brightness has to agree
+            assert!(radiance.luminance() > 0.0);
+            assert!(map.radiance(direction).luminance() > 0.0);
+        }
+    }
+
+    #[test]
+    fn pdf_integrates_the_radiance() {
+        // Each pixel covers 2 pi / width of longitude between two rings of
+        // latitude, so the map's total power has a closed form
+        let map = map();
+        let (width, height) = (map.image.width, map.image.height);
+        let mut expected = 0.0;
+        for y in 0..height {
+            let band = (PI * y as f64 / height as f64).cos() - (PI * (y + 1) as f64 / height as f64).cos();
+            for x in 0..width {
+                expected += map.image.get(x, y).luminance() * map.intensity * band * 2.0 * PI / width as f64;
+            }
+        }
+
+        let mut rng = Rng::new(2);
+        let count = 200_000;
+        let mut estimate = 0.0;
+        for _ in 0..count {
+            let (_, radiance, pdf) = map.sample(&mut rng).unwrap();
+            estimate += radiance.luminance() / pdf;
+        }
+        estimate /= count as f64;
+        assert!((estimate - expected).abs() < 0.02 * expected, "{} != {}", estimate, expected);
+    }
+
+    #[test]
+    fn black_maps_have_nothing_to_sample() {
+        let image = Image {
+            width: 2,
+            height: 2,
+            pixels: vec![Color::black(); 4],
+        };
+        let map = EnvironmentMap::new(image, 1.0, 0.0);
+        assert!(map.sample(&mut Rng::new(3)).is_none());
+        assert_eq!(map.radiance(Vec3::new(0.0, 1.0, 0.0)), Color::black());
+    }
+}
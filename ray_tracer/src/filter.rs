@@ -0,0 +1,125 @@
+// Pixel reconstruction filters
+//
+// Each pixel is the weighted average of the samples taken within `radius`
+// pixels of its center, weighted by the filter. All filters here are
+// separable: the 2D weight is the product of two 1D weights.
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Filter {
+    // Equal weight everywhere
+    Box { radius: f64 },
+    // Linear falloff to zero at the radius
+    Tent { radius: f64 },
+    // Gaussian shifted down to reach zero at the radius
+    Gaussian { radius: f64, alpha: f64 },
+    // Mitchell-Netravali cubic (Mitchell & Netravali 1988)
+    Mitchell { radius: f64, b: f64, c: f64 },
+}
+
+impl Filter {
+    pub fn radius(&self) -> f64 {
+        match *self {
+            Filter::Box { radius }
+            | Filter::Tent { radius }
+            | Filter::Gaussian { radius, .. }
+            | Filter::Mitchell { radius, .. } => radius,
+        }
+    }
+
+    // Weight of a sample at offset (dx, dy) from the pixel center
+    pub fn evaluate(&self, dx: f64, dy: f64) -> f64 {
+        self.evaluate_1d(dx) * self.evaluate_1d(dy)
+    }
+
+    fn evaluate_1d(&self, x: f64) -> f64 {
+        let x = x.abs();
+        match *self {
+            Filter::Box { radius } => {
+                if x <= radius {
+                    1.0
+                } else {
+                    0.0
+                }
+            }
+            Filter::Tent { radius } => (radius - x).max(0.0),
+            Filter::Gaussian { radius, alpha } => {
+                ((-alpha * x * x).exp() - (-alpha * radius * radius).exp()).max(0.0)
+            }
+            Filter::Mitchell { radius, b, c } => {
+                // The cubic is defined over [-2, 2]; stretch it to the radius
+                let x = 2.0 * x / radius;
+                if x >= 2.0 {
+                    0.0
+                } else if x >= 1.0 {
+                    ((-b - 6.0 * c) * x.powi(3)
+                        + (6.0 * b + 30.0 * c) * x * x
+                        + (-12.0 * b - 48.0 * c) * x
+                        + (8.0 * b + 24.0 * c))
+                        / 6.0
+                } else {
+                    ((12.0 - 9.0 * b - 6.0 * c) * x.powi(3)
+                        + (-18.0 + 12.0 * b + 6.0 * c) * x * x
+                        + (6.0 - 2.0 * b))
+                        / 6.0
+                }
+            }
+        }
+    }
+}
+
+impl Default for Filter {
+    // One sample area per pixel, no overlap between neighbors
+    fn default() -> Self {
+        Filter::Box { radius: 0.5 }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MITCHELL: Filter = Filter::Mitchell {
+        radius: 2.0,
+        b: 1.0 / 3.0,
+        c: 1.0 / 3.0,
+    };
+
+    #[test]
+    fn weights_fall_to_zero_at_the_radius() {
+        let filters = [
+            Filter::Box { radius: 0.5 },
+            Filter::Tent { radius: 1.0 },
+            Filter::Gaussian { radius: 1.5, alpha: 2.0 },
+            MITCHELL,
+        ];
+        for filter in filters {
+            let radius = filter.radius();
+            assert!(filter.evaluate(0.0, 0.0) > 0.0);
+            assert!(filter.evaluate(radius * 1.01, 0.0).abs() < 1e-12);
+            assert!(filter.evaluate(0.0, -radius * 1.01).abs() < 1e-12);
+            // Symmetric and separable
+            assert_eq!(filter.evaluate(0.3, -0.2), filter.evaluate(-0.3, 0.2));
+            let product = filter.evaluate(0.3, 0.0) * filter.evaluate(0.0, 0.2) / filter.evaluate(0.0, 0.0);
+            assert!((filter.evaluate(0.3, 0.2) - product).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn known_weights() {
+        assert_eq!(Filter::Box { radius: 0.5 }.evaluate(0.5, -0.5), 1.0);
+        assert_eq!(Filter::Tent { radius: 1.0 }.evaluate(0.25, 0.5), 0.75 * 0.5);
+        let gaussian = Filter::Gaussian { radius: 1.0, alpha: 1.0 };
+        assert!((gaussian.evaluate(0.0, 0.0) - (1.0 - (-1.0f64).exp()).powi(2)).abs() < 1e-12);
+    }
+
+    #[test]
+    fn mitchell_has_negative_lobes() {
+        // Stretched over a radius of 2 the cubic is used as published:
+        // (6 - 2b) / 6 at the center, continuous at 1, negative past it
+        assert!((MITCHELL.evaluate(0.0, 0.0) - (8.0f64 / 9.0).powi(2)).abs() < 1e-12);
+        let near = MITCHELL.evaluate(1.0 - 1e-9, 0.0);
+        let far = MITCHELL.evaluate(1.0 + 1e-9, 0.0);
+        assert!((near - far).abs() < 1e-6);
+        assert!(MITCHELL.evaluate(1.5, 0.0) < 0.0);
+    }
+}
@@ -0,0 +1,79 @@
+// Shared ray-object intersection interface
+
+use crate::aabb::Aabb;
+use crate::material::Material;
+use crate::sampling::orthonormal_basis;
+use crate::vec3::{Point3, Vec3};
+use crate::Ray;
+
+// Everything we know about the closest surface point along a ray
+#[derive(Debug, Clone, Copy)]
+pub struct Hit<'a> {
+    pub t: f64,
+    pub point: Point3,
+    // Always faces against the incoming ray, so flat shapes are two-sided;
+    // `front_face` records whether that is the outward side.
+    pub normal: Vec3,
+    pub front_face: bool,
+    // Surface coordinates for texture lookups, usually within [0, 1]
+    pub uv: (f64, f64),
+    // Unit directions in which u and v increase along the surface, for
+    // normal mapping. Shapes without a natural parameterization get an
+    // arbitrary frame around the normal.
+    pub tangent: Vec3,
+    pub bitangent: Vec3,
+    pub material: &'a Material,
+}
+
+impl<'a> Hit<'a> {
+    pub fn new(ray: &Ray, t: f64, outward_normal: Vec3, uv: (f64, f64), material: &'a Material) -> Self {
+        let front_face = ray.direction.dot(&outward_normal) < 0.0;
+        let (tangent, bitangent) = orthonormal_basis(outward_normal);
+        Hit {
+            t,
+            point: ray.point_at(t),
+            normal: if front_face {
+                outward_normal
+            } else {
+                -outward_normal
+            },
+            front_face,
+            uv,
+            tangent,
+            bitangent,
+            material,
+        }
+    }
+
+    pub fn with_tangents(mut self, tangent: Vec3, bitangent: Vec3) -> Self {
+        self.tangent = tangent.normalize();
+        self.bitangent = bitangent.normalize();
+        self
+    }
+}
+
+pub trait Hittable: Send + Sync {
+    // Returns the closest hit with `t_min < t < t_max`, if any
+    fn intersect(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<Hit<'_>>;
+
+    // Box enclosing the whole object, or `None` if it is infinite
+    fn bounding_box(&self) -> Option<Aabb>;
+
+    // Whether `point` lies inside the solid at `time`. By default a ray is
+    // cast from the point: from inside it leaves through the back of the
+    // first surface it meets, from outside it meets a front face or
+    // nothing. That only holds for closed, bounded shapes, so the others
+    // answer exactly.
+    fn contains(&self, point: Point3, time: f64) -> bool {
+        let probe = Ray::new(point, PROBE).with_time(time);
+        self.intersect(&probe, 0.0, f64::INFINITY).is_some_and(|hit| !hit.front_face)
+    }
+}
+
+// Direction of the rays cast by `Hittable::contains`, unlikely to run
+// exactly along the face of a box or mesh
+const PROBE: Vec3 = Vec3 {
+    x: 0.267_261_241_912_424_4,
+    y: 0.534_522_483_824_848_8,
+    z: 0.801_783_725_737_273_2,
+};
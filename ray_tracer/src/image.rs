@@ -0,0 +1,17 @@
+// In-memory images
+
+use crate::color::Color;
+
+// Linear RGB pixels stored row by row, top row first
+#[derive(Debug, Clone)]
+pub struct Image {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<Color>,
+}
+
+impl Image {
+    pub fn get(&self, x: usize, y: usize) -> Color {
+        self.pixels[y * self.width + x]
+    }
+}
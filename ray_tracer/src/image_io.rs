@@ -0,0 +1,520 @@
+// Image file readers and writers
+
+use crate::color::Color;
+use crate::image::Image;
+use std::{
+    fs::{self, File},
+    io::{self, BufWriter, Cursor, Write},
+    path::Path,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ImageFormat {
+    // Plain-text `P3` PPM
+    PpmAscii,
+    // Binary `P6` PPM
+    PpmBinary,
+    Png8,
+    Png16,
+    // OpenEXR with 32-bit float channels; keeps values above 1.0
+    Exr,
+}
+
+// How the channels of 8 and 16-bit image files are encoded
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ColorSpace {
+    // Gamma-encoded colors, as painted or photographed; decoded to linear
+    // on load
+    Srgb,
+    // Data such as normal maps, kept as stored
+    Linear,
+}
+
+impl ColorSpace {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "srgb" => Some(ColorSpace::Srgb),
+            "linear" => Some(ColorSpace::Linear),
+            _ => None,
+        }
+    }
+}
+
+impl ImageFormat {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "ppm" | "ppm-ascii" => Some(ImageFormat::PpmAscii),
+            "ppm-binary" => Some(ImageFormat::PpmBinary),
+            "png" | "png8" => Some(ImageFormat::Png8),
+            "png16" => Some(ImageFormat::Png16),
+            "exr" => Some(ImageFormat::Exr),
+            _ => None,
+        }
+    }
+
+    // Whether the format stores linear floating point values, which are
+    // written without an output transform
+    pub fn is_float(self) -> bool {
+        self == ImageFormat::Exr
+    }
+
+    // Guesses the format from the file extension
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "ppm" => Some(ImageFormat::PpmAscii),
+            "png" => Some(ImageFormat::Png8),
+            "exr" => Some(ImageFormat::Exr),
+            _ => None,
+        }
+    }
+}
+
+pub fn write(image: &Image, path: &Path, format: ImageFormat) -> io::Result<()> {
+    match format {
+        ImageFormat::PpmAscii => write_ppm_ascii(image, path),
+        ImageFormat::PpmBinary => write_ppm_binary(image, path),
+        ImageFormat::Png8 => write_png(image, path, png::BitDepth::Eight),
+        ImageFormat::Png16 => write_png(image, path, png::BitDepth::Sixteen),
+        ImageFormat::Exr => write_exr(image, path),
+    }
+}
+
+// Reads a PPM (`P3` or `P6`), PNG, Radiance HDR or OpenEXR file, telling
+// them apart by their first bytes. Channels of 8 and 16-bit formats are
+// scaled to [0, 1] and decoded from `color_space`; HDR and EXR values are
+// linear already and kept as they are.
+pub fn read(path: &Path, color_space: ColorSpace) -> io::Result<Image> {
+    let data = fs::read(path)?;
+    let image = if data.starts_with(b"P3") || data.starts_with(b"P6") {
+        decode(read_ppm(&data)?, color_space)
+    } else if data.starts_with(b"\x89PNG") {
+        decode(read_png(data)?, color_space)
+    } else if data.starts_with(b"#?") {
+        read_hdr(&data)?
+    } else if data.starts_with(&[0x76, 0x2f, 0x31, 0x01]) {
+        read_exr(data)?
+    } else {
+        return Err(invalid_data("unsupported image format (expected PPM, PNG, HDR or EXR)"));
+    };
+    // Texture and environment lookups wrap coordinates around the size
+    if image.width == 0 || image.height == 0 {
+        return Err(invalid_data("image has no pixels"));
+    }
+    Ok(image)
+}
+
+fn decode(image: Image, color_space: ColorSpace) -> Image {
+    match color_space {
+        ColorSpace::Srgb => Image {
+            pixels: image.pixels.iter().map(|&color| color.map(srgb_to_linear)).collect(),
+            ..image
+        },
+        ColorSpace::Linear => image,
+    }
+}
+
+// Inverse of the sRGB transfer curve in tone_map
+fn srgb_to_linear(x: f64) -> f64 {
+    if x <= 0.04045 {
+        x / 12.92
+    } else {
+        ((x + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+fn read_ppm(data: &[u8]) -> io::Result<Image> {
+    // Header: magic, width, height and maximum value, separated by
+    // whitespace and `#` comments
+    let mut pos = 0;
+    let mut header = Vec::new();
+    while header.len() < 4 {
+        while pos < data.len() && (data[pos].is_ascii_whitespace() || data[pos] == b'#') {
+            if data[pos] == b'#' {
+                while pos < data.len() && data[pos] != b'\n' {
+                    pos += 1;
+                }
+            } else {
+                pos += 1;
+            }
+        }
+        let start = pos;
+        while pos < data.len() && !data[pos].is_ascii_whitespace() {
+            pos += 1;
+        }
+        if start == pos {
+            return Err(invalid_data("truncated PPM header"));
+        }
+        header.push(String::from_utf8_lossy(&data[start..pos]).into_owned());
+    }
+    let number = |field: &str| {
+        field
+            .parse::<usize>()
+            .map_err(|_| invalid_data("invalid number in PPM header"))
+    };
+    let (width, height, max_value) = (number(&header[1])?, number(&header[2])?, number(&header[3])?);
+    if max_value == 0 || max_value > 65535 {
+        return Err(invalid_data("PPM maximum value must be between 1 and 65535"));
+    }
+
+    let count = width
+        .checked_mul(height)
+        .and_then(|pixels| pixels.checked_mul(3))
+        .ok_or_else(|| invalid_data("PPM image is too large"))?;
+    let samples: Vec<usize> = if header[0] == "P3" {
+        String::from_utf8_lossy(&data[pos..])
+            .split_ascii_whitespace()
+            .take(count)
+            .map(number)
+            .collect::<io::Result<_>>()?
+    } else {
+        // A single whitespace byte separates the header from the pixels
+        let body = data.get(pos + 1..).unwrap_or(&[]);
+        if max_value < 256 {
+            body.iter().take(count).map(|&b| b as usize).collect()
+        } else {
+            body.chunks_exact(2)
+                .take(count)
+                .map(|pair| u16::from_be_bytes([pair[0], pair[1]]) as usize)
+                .collect()
+        }
+    };
+    if samples.len() < count {
+        return Err(invalid_data("PPM file has fewer pixels than its header says"));
+    }
+
+    let scale = 1.0 / max_value as f64;
+    let pixels = samples
+        .chunks_exact(3)
+        .map(|rgb| Color::new(rgb[0] as f64 * scale, rgb[1] as f64 * scale, rgb[2] as f64 * scale))
+        .collect();
+    Ok(Image { width, height, pixels })
+}
+
+fn read_png(data: Vec<u8>) -> io::Result<Image> {
+    let mut decoder = png::Decoder::new(Cursor::new(data));
+    // Palettes and low bit depths are expanded to 8 bits; 16-bit images
+    // keep their precision
+    decoder.set_transformations(png::Transformations::EXPAND);
+    let mut reader = decoder.read_info()?;
+    let mut buffer = vec![0; reader.output_buffer_size().ok_or_else(|| invalid_data("PNG image is too large"))?];
+    let info = reader.next_frame(&mut buffer)?;
+
+    // Grayscale is spread to all three channels; alpha is ignored
+    let channels = info.color_type.samples();
+    let bytes = if info.bit_depth == png::BitDepth::Sixteen { 2 } else { 1 };
+    let pixels = buffer[..info.line_size * info.height as usize]
+        .chunks_exact(info.line_size)
+        .flat_map(|line| line[..info.width as usize * channels * bytes].chunks_exact(channels * bytes))
+        .map(|pixel| {
+            let channel = |i: usize| {
+                let i = if channels < 3 { 0 } else { i };
+                match bytes {
+                    2 => u16::from_be_bytes([pixel[2 * i], pixel[2 * i + 1]]) as f64 / 65535.0,
+                    _ => pixel[i] as f64 / 255.0,
+                }
+            };
+            Color::new(channel(0), channel(1), channel(2))
+        })
+        .collect();
+    Ok(Image {
+        width: info.width as usize,
+        height: info.height as usize,
+        pixels,
+    })
+}
+
+// Radiance RGBE: a text header, a resolution line and scanlines that are
+// either flat or run-length encoded one channel at a time
+fn read_hdr(data: &[u8]) -> io::Result<Image> {
+    let mut lines = data.split(|&b| b == b'\n');
+    let mut pos = 0;
+    let mut next_line = || {
+        let line = lines.next()?;
+        pos += line.len() + 1;
+        Some(String::from_utf8_lossy(line).into_owned())
+    };
+
+    // Header ends at the first empty line
+    loop {
+        match next_line() {
+            None => return Err(invalid_data("truncated HDR header")),
+            Some(line) if line.trim().is_empty() => break,
+            Some(line) if line.starts_with("FORMAT=") && line.trim() != "FORMAT=32-bit_rle_rgbe" => {
+                return Err(invalid_data("only RGBE HDR files are supported"));
+            }
+            Some(_) => {}
+        }
+    }
+    let resolution = next_line().ok_or_else(|| invalid_data("missing HDR resolution"))?;
+    let fields: Vec<&str> = resolution.split_whitespace().collect();
+    let (height, width) = match fields.as_slice() {
+        ["-Y", h, "+X", w] => (h.parse::<usize>(), w.parse::<usize>()),
+        _ => return Err(invalid_data("unsupported HDR orientation (expected -Y h +X w)")),
+    };
+    let (Ok(height), Ok(width)) = (height, width) else {
+        return Err(invalid_data("invalid HDR resolution"));
+    };
+    let count = width.checked_mul(height).ok_or_else(|| invalid_data("HDR image is too large"))?;
+
+    let mut body = &data[pos.min(data.len())..];
+    let mut pixels = Vec::with_capacity(count);
+    let mut scanline = vec![[0u8; 4]; width];
+    for _ in 0..height {
+        let rle = (8..0x8000).contains(&width)
+            && body.len() >= 4
+            && body[0] == 2
+            && body[1] == 2
+            && ((body[2] as usize) << 8 | body[3] as usize) == width;
+        if rle {
+            body = &body[4..];
+            for channel in 0..4 {
+                let mut x = 0;
+                while x < width {
+                    let (&count, rest) = body.split_first().ok_or_else(|| invalid_data("truncated HDR data"))?;
+                    if count > 128 {
+                        // A run of one repeated value
+                        let count = count as usize - 128;
+                        let &value = rest.first().ok_or_else(|| invalid_data("truncated HDR data"))?;
+                        if x + count > width {
+                            return Err(invalid_data("HDR run overflows its scanline"));
+                        }
+                        scanline[x..x + count].iter_mut().for_each(|p| p[channel] = value);
+                        x += count;
+                        body = &rest[1..];
+                    } else {
+                        let count = count as usize;
+                        if count == 0 || x + count > width || rest.len() < count {
+                            return Err(invalid_data("invalid HDR run"));
+                        }
+                        for (p, &value) in scanline[x..x + count].iter_mut().zip(rest) {
+                            p[channel] = value;
+                        }
+                        x += count;
+                        body = &rest[count..];
+                    }
+                }
+            }
+        } else {
+            if body.len() < width * 4 {
+                return Err(invalid_data("truncated HDR data"));
+            }
+            for (p, rgbe) in scanline.iter_mut().zip(body.chunks_exact(4)) {
+                p.copy_from_slice(rgbe);
+            }
+            body = &body[width * 4..];
+        }
+
+        pixels.extend(scanline.iter().map(|&[r, g, b, e]| {
+            if e == 0 {
+                Color::black()
+            } else {
+                let scale = 2f64.powi(e as i32 - 136);
+                Color::new(r as f64 * scale, g as f64 * scale, b as f64 * scale)
+            }
+        }));
+    }
+    Ok(Image { width, height, pixels })
+}
+
+fn read_exr(data: Vec<u8>) -> io::Result<Image> {
+    use exr::prelude::*;
+    let image = read()
+        .no_deep_data()
+        .largest_resolution_level()
+        .rgba_channels(
+            |resolution, _| crate::image::Image {
+                width: resolution.width(),
+                height: resolution.height(),
+                pixels: vec![Color::black(); resolution.area()],
+            },
+            |image, position, (r, g, b, _): (f32, f32, f32, f32)| {
+                image.pixels[position.y() * image.width + position.x()] = Color::new(r as f64, g as f64, b as f64);
+            },
+        )
+        .first_valid_layer()
+        .all_attributes()
+        .from_buffered(Cursor::new(data))
+        .map_err(|err| invalid_data(&err.to_string()))?;
+    Ok(image.layer_data.channel_data.pixels)
+}
+
+fn to_u8(x: f64) -> u8 {
+    (x.clamp(0.0, 1.0) * 255.0) as u8
+}
+
+fn to_u16(x: f64) -> u16 {
+    (x.clamp(0.0, 1.0) * 65535.0) as u16
+}
+
+fn write_ppm_ascii(image: &Image, path: &Path) -> io::Result<()> {
+    let mut file = BufWriter::new(File::create(path)?);
+    writeln!(file, "P3\n{} {}\n255", image.width, image.height)?;
+    for color in &image.pixels {
+        writeln!(file, "{} {} {}", to_u8(color.r), to_u8(color.g), to_u8(color.b))?;
+    }
+    file.flush()
+}
+
+fn write_ppm_binary(image: &Image, path: &Path) -> io::Result<()> {
+    let mut file = BufWriter::new(File::create(path)?);
+    writeln!(file, "P6\n{} {}\n255", image.width, image.height)?;
+    let bytes: Vec<u8> = image
+        .pixels
+        .iter()
+        .flat_map(|color| [to_u8(color.r), to_u8(color.g), to_u8(color.b)])
+        .collect();
+    file.write_all(&bytes)?;
+    file.flush()
+}
+
+fn write_png(image: &Image, path: &Path, depth: png::BitDepth) -> io::Result<()> {
+    let file = BufWriter::new(File::create(path)?);
+    let mut encoder = png::Encoder::new(file, image.width as u32, image.height as u32);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(depth);
+
+    let channels = image.pixels.iter().flat_map(|color: &Color| [color.r, color.g, color.b]);
+    let data: Vec<u8> = match depth {
+        png::BitDepth::Sixteen => channels.flat_map(|x| to_u16(x).to_be_bytes()).collect(),
+        _ => channels.map(to_u8).collect(),
+    };
+
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(&data)?;
+    writer.finish()?;
+    Ok(())
+}
+
+fn write_exr(image: &Image, path: &Path) -> io::Result<()> {
+    exr::prelude::write_rgb_file(path, image.width, image.height, |x, y| {
+        let color = image.get(x, y);
+        (color.r as f32, color.g as f32, color.b as f32)
+    })
+    .map_err(|err| io::Error::other(err.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A 3x2 image with a value above 1 for the float formats
+    fn image() -> Image {
+        Image {
+            width: 3,
+            height: 2,
+            pixels: vec![
+                Color::black(),
+                Color::new(0.2, 0.8, 0.25),
+                Color::new(0.4, 0.6, 0.25),
+                Color::new(0.6, 0.4, 0.25),
+                Color::white(),
+                Color::new(4.0, 0.5, 0.0),
+            ],
+        }
+    }
+
+    // Writes `image` and reads it back, in a scratch file of its own
+    fn round_trip(image: &Image, format: ImageFormat) -> Image {
+        let dir = std::env::temp_dir().join(format!("ray_tracer_image_io_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(format!("{:?}", format));
+        write(image, &path, format).unwrap();
+        let read = read(&path, ColorSpace::Linear).unwrap();
+        fs::remove_file(&path).unwrap();
+        read
+    }
+
+    fn assert_close(a: &Image, b: &Image, tolerance: f64) {
+        assert_eq!((a.width, a.height), (b.width, b.height));
+        for (a, b) in a.pixels.iter().zip(&b.pixels) {
+            let error = (a.r - b.r).abs().max((a.g - b.g).abs()).max((a.b - b.b).abs());
+            assert!(error <= tolerance, "{:?} != {:?}", a, b);
+        }
+    }
+
+    #[test]
+    fn integer_formats_round_trip_within_a_step() {
+        let image = image();
+        // Values above 1 are clipped
+        let mut clipped = image.clone();
+        clipped.pixels[5].r = 1.0;
+        for format in [ImageFormat::PpmAscii, ImageFormat::PpmBinary, ImageFormat::Png8] {
+            assert_close(&round_trip(&image, format), &clipped, 1.0 / 255.0);
+        }
+        assert_close(&round_trip(&image, ImageFormat::Png16), &clipped, 1.0 / 65535.0);
+    }
+
+    #[test]
+    fn exr_keeps_float_values() {
+        let image = image();
+        assert_close(&round_trip(&image, ImageFormat::Exr), &image, 1e-6);
+    }
+
+    #[test]
+    fn decodes_srgb_on_request() {
+        let linear = Image {
+            width: 2,
+            height: 1,
+            pixels: vec![Color::new(0.0, 0.5, 1.0), Color::new(0.04, 0.2, 0.8)],
+        };
+        let decoded = decode(linear.clone(), ColorSpace::Srgb);
+        assert_eq!(decoded.pixels[0].r, 0.0);
+        assert!((decoded.pixels[0].g - 0.214).abs() < 1e-3);
+        assert_eq!(decoded.pixels[0].b, 1.0);
+        assert!((decoded.pixels[1].r - 0.04 / 12.92).abs() < 1e-12);
+        assert_eq!(decode(linear.clone(), ColorSpace::Linear).pixels, linear.pixels);
+    }
+
+    #[test]
+    fn rejects_unknown_and_empty_images() {
+        let dir = std::env::temp_dir().join(format!("ray_tracer_image_io_bad_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let unknown = dir.join("unknown");
+        fs::write(&unknown, "GIF89a").unwrap();
+        assert!(read(&unknown, ColorSpace::Srgb).is_err());
+        let empty = dir.join("empty.ppm");
+        fs::write(&empty, "P3\n0 0\n255\n").unwrap();
+        assert!(read(&empty, ColorSpace::Srgb).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn reads_flat_and_run_length_encoded_hdr() {
+        // Two flat pixels: mantissas of 128 scaled by 2^(e - 136)
+        let mut data = b"#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y 1 +X 2\n".to_vec();
+        data.extend([128, 64, 0, 129, 128, 128, 128, 131]);
+        let image = read_hdr(&data).unwrap();
+        assert_eq!((image.width, image.height), (2, 1));
+        assert_eq!(image.pixels, vec![Color::new(1.0, 0.5, 0.0), Color::new(4.0, 4.0, 4.0)]);
+
+        // One encoded scanline of 8: runs for red, green and blue, literal
+        // exponents
+        let mut data = b"#?RADIANCE\n\n-Y 1 +X 8\n".to_vec();
+        data.extend([2, 2, 0, 8]);
+        data.extend([136, 128]);
+        data.extend([136, 64]);
+        data.extend([132, 0, 132, 128]);
+        data.extend([8, 129, 129, 129, 129, 129, 129, 129, 0]);
+        let image = read_hdr(&data).unwrap();
+        assert_eq!(image.pixels[..4], [Color::new(1.0, 0.5, 0.0); 4]);
+        assert_eq!(image.pixels[4..7], [Color::new(1.0, 0.5, 1.0); 3]);
+        // A zero exponent is black whatever the mantissas
+        assert_eq!(image.pixels[7], Color::black());
+    }
+
+    #[test]
+    fn rejects_bad_hdr_files() {
+        let header = b"#?RADIANCE\nFORMAT=32-bit_rle_xyze\n\n-Y 1 +X 1\n".to_vec();
+        assert!(read_hdr(&header).is_err());
+        let mut truncated = b"#?RADIANCE\n\n-Y 2 +X 1\n".to_vec();
+        truncated.extend([128, 128, 128, 129]);
+        assert!(read_hdr(&truncated).is_err());
+        let overflowing = [b"#?RADIANCE\n\n-Y 1 +X 8\n".as_slice(), &[2, 2, 0, 8, 137, 1]].concat();
+        assert!(read_hdr(&overflowing).is_err());
+    }
+}
@@ -0,0 +1,215 @@
+// Light transport algorithms
+
+use crate::color::Color;
+use crate::material::Scatter;
+use crate::rng::Rng;
+use crate::sampling;
+use crate::{Ray, Scene};
+
+// Bounces after which paths may be terminated by Russian roulette
+const ROULETTE_START: u32 = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Integrator {
+    // Whitted-style: direct light from each light plus a constant ambient
+    // term, with recursive mirror and glass bounces
+    Direct,
+    // Monte Carlo path tracing with global illumination
+    Path,
+}
+
+impl Integrator {
+    pub fn radiance(&self, scene: &Scene, ray: &Ray, max_depth: u32, rng: &mut Rng) -> Color {
+        match self {
+            Integrator::Direct => scene.trace(ray, max_depth, rng),
+            Integrator::Path => path_trace(scene, ray, max_depth, rng),
+        }
+    }
+}
+
+// Follows a single random path from the camera. Diffuse surfaces sample
+// the lights directly at every bounce and continue in a cosine-weighted
+// direction, so the ambient term is replaced by actual indirect light.
+fn path_trace(scene: &Scene, ray: &Ray, max_depth: u32, rng: &mut Rng) -> Color {
+    let mut radiance = Color::black();
+    let mut throughput = Color::white();
+    let mut ray = *ray;
+    let mut after_diffuse = false;
+
+    for bounce in 0..=max_depth {
+        let hit = scene.hit(&ray, 0.0, f64::INFINITY);
+
+        // Scattering in a medium before the surface: sample the lights as
+        // on a diffuse surface, then continue in a direction drawn from the
+        // phase function
+        if let Some((t, medium)) = scene.medium_event(&ray, hit.map_or(f64::INFINITY, |hit| hit.t), rng) {
+            through_fog(scene, t, &mut radiance, &mut throughput);
+            let point = ray.point_at(t);
+            radiance += throughput * scene.medium_light(&ray, point, medium, rng);
+            throughput *= medium.albedo;
+            after_diffuse = true;
+            ray = Ray::new(point, medium.phase.sample(ray.direction, rng)).with_time(ray.time);
+            if bounce >= ROULETTE_START && !survives(&mut throughput, rng) {
+                break;
+            }
+            continue;
+        }
+
+        let Some(hit) = hit else {
+            // An environment light was already sampled directly at the
+            // last diffuse bounce
+            if !(after_diffuse && scene.background.is_light()) {
+                radiance += throughput * scene.background.color(ray.direction);
+            }
+            break;
+        };
+        through_fog(scene, hit.t, &mut radiance, &mut throughput);
+
+        let next = match hit.material.scatter(&ray, &hit, rng) {
+            Scatter::Diffuse(shading) => {
+                let direct = scene.direct_light(&ray, &hit, &shading, rng);
+                radiance += throughput * direct;
+                // Only the diffuse part carries indirect light onwards
+                throughput *= shading.albedo * shading.kd;
+                let direction = sampling::cosine_hemisphere(hit.normal, rng);
+                after_diffuse = true;
+                Ray::new(hit.point + hit.normal * 0.001, direction).with_time(ray.time)
+            }
+            Scatter::Rays(rays) => {
+                // Follow one of the rays, picked in proportion to its weight
+                let total: f64 = rays.iter().map(|(_, weight)| weight.average()).sum();
+                if total <= 0.0 {
+                    break;
+                }
+                let mut pick = rng.next_f64() * total;
+                let (next, weight) = *rays
+                    .iter()
+                    .find(|(_, weight)| {
+                        pick -= weight.average();
+                        pick < 0.0
+                    })
+                    .unwrap_or(rays.last().unwrap());
+                throughput *= weight * (total / weight.average());
+                after_diffuse = false;
+                next
+            }
+        };
+
+        if bounce >= ROULETTE_START && !survives(&mut throughput, rng) {
+            break;
+        }
+        ray = next;
+    }
+
+    radiance
+}
+
+// Russian roulette: randomly end dim paths, boosting the survivors so the
+// estimate stays unbiased
+fn survives(throughput: &mut Color, rng: &mut Rng) -> bool {
+    let survival = throughput.max_channel().clamp(0.05, 0.95);
+    if rng.next_f64() > survival {
+        return false;
+    }
+    *throughput *= 1.0 / survival;
+    true
+}
+
+// Fog over a path segment of length `distance`: it adds its own color and
+// hides part of whatever lies beyond
+fn through_fog(scene: &Scene, distance: f64, radiance: &mut Color, throughput: &mut Color) {
+    if let Some(fog) = scene.fog {
+        let transmittance = fog.transmittance(distance);
+        *radiance += *throughput * fog.color * (1.0 - transmittance);
+        *throughput *= transmittance;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::environment::Background;
+    use crate::hittable::Hittable;
+    use crate::light::{Falloff, Light, LightKind};
+    use crate::material::Material;
+    use crate::shapes::Sphere;
+    use crate::texture::Texture;
+    use crate::vec3::{Point3, Vec3};
+    use std::sync::Arc;
+
+    fn sphere(radius: f64, albedo: f64) -> Box<dyn Hittable> {
+        let material = Arc::new(Material::Lambertian {
+            albedo: Texture::Solid(Color::new(albedo, albedo, albedo)),
+        });
+        Box::new(Sphere::new(Point3::origin(), radius, material))
+    }
+
+    // Average radiance along random rays from the origin
+    fn average(scene: &Scene, max_depth: u32, count: u32) -> f64 {
+        let mut rng = Rng::new(1);
+        let total: f64 = (0..count)
+            .map(|_| {
+                let ray = Ray::new(Point3::origin(), rng.in_unit_sphere());
+                Integrator::Path.radiance(scene, &ray, max_depth, &mut rng).average()
+            })
+            .sum();
+        total / count as f64
+    }
+
+    #[test]
+    fn white_sky_furnace() {
+        // A convex diffuse object under a uniform sky reflects its albedo
+        // of the sky on every path, whichever way it bounces
+        let scene = Scene::new(vec![sphere(1.0, 0.6)], Vec::new(), 0.0, Background::Solid(Color::white()));
+        let mut rng = Rng::new(2);
+        for _ in 0..100 {
+            let target = Point3::origin() + rng.in_unit_sphere() * 0.5;
+            let ray = Ray::new(Point3::new(0.0, 0.0, 5.0), target - Point3::new(0.0, 0.0, 5.0));
+            let radiance = Integrator::Path.radiance(&scene, &ray, 8, &mut rng);
+            assert!((radiance.average() - 0.6).abs() < 1e-9, "{:?}", radiance);
+        }
+    }
+
+    #[test]
+    fn closed_room_furnace() {
+        // Inside a closed sphere lit from its center, every wall gets the
+        // same direct light and sees only other walls, so each bounce adds
+        // `albedo` times the last: a / (1 - a) in total
+        let light = Light::new(
+            LightKind::Point {
+                position: Point3::origin(),
+            },
+            Color::white(),
+            Falloff::None,
+            1,
+        );
+        let scene = Scene::new(vec![sphere(2.0, 0.5)], vec![light], 0.0, Background::Solid(Color::black()));
+        let estimate = average(&scene, 64, 20_000);
+        assert!((estimate - 1.0).abs() < 0.03, "{}", estimate);
+
+        // Without bounces only the direct light is left
+        assert!((average(&scene, 0, 100) - 0.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn mirrors_show_what_they_reflect() {
+        let material = Arc::new(Material::Metal {
+            albedo: Texture::Solid(Color::new(0.8, 0.8, 0.8)),
+            roughness: 0.0,
+        });
+        let mirror: Box<dyn Hittable> = Box::new(Sphere::new(Point3::origin(), 1.0, material));
+        let sky = Background::Gradient {
+            bottom: Color::black(),
+            top: Color::white(),
+        };
+        let scene = Scene::new(vec![mirror], Vec::new(), 0.0, sky);
+
+        // Straight down onto the top of the ball, back up into the white
+        // zenith
+        let ray = Ray::new(Point3::new(0.0, 5.0, 0.0), Vec3::new(0.0, -1.0, 0.0));
+        let radiance = Integrator::Path.radiance(&scene, &ray, 8, &mut Rng::new(3));
+        assert!((radiance.average() - 0.8).abs() < 1e-9);
+        // Out of bounces, nothing is reflected
+        assert_eq!(Integrator::Path.radiance(&scene, &ray, 0, &mut Rng::new(3)), Color::black());
+    }
+}
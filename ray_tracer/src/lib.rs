@@ -0,0 +1,338 @@
+// Ray tracer library
+//
+// Scenes can be loaded from a scene file with `scene_file::load` or built
+// in code from shapes, materials and lights with `Scene::new`. `render`
+// turns a scene, a camera and `RenderSettings` into a `Framebuffer`, and
+// `image_io` writes the result. The command line tool is a thin wrapper
+// around this API.
+
+pub mod aabb;
+pub mod animation;
+pub mod aov;
+mod bvh;
+pub mod camera;
+pub mod color;
+pub mod denoise;
+pub mod environment;
+pub mod filter;
+pub mod hittable;
+pub mod image;
+pub mod image_io;
+pub mod integrator;
+pub mod light;
+pub mod material;
+pub mod medium;
+pub mod motion;
+pub mod normal_map;
+pub mod obj;
+mod progress;
+pub mod render;
+pub mod rng;
+pub mod sampler;
+mod sampling;
+pub mod scene_file;
+pub mod shapes;
+pub mod texture;
+pub mod tone_map;
+pub mod transform;
+pub mod vec3;
+
+use aabb::Aabb;
+use bvh::Bvh;
+use environment::Background;
+use hittable::{Hit, Hittable};
+use light::{Light, LightSample};
+use material::{Material, Scatter, Shading};
+use medium::{Fog, Medium};
+use rng::Rng;
+use std::f64::consts::PI;
+use std::sync::Arc;
+
+// The types most programs need, so they can be used without the module path
+pub use camera::{Camera, Projection};
+pub use color::Color;
+pub use image::Image;
+pub use render::{render, render_progressive, Framebuffer};
+pub use scene_file::{RenderSettings, SceneDescription, SceneError};
+pub use vec3::{Point3, Vec3};
+
+// Ray structure
+#[derive(Debug, Clone, Copy)]
+pub struct Ray {
+    pub origin: Point3,
+    pub direction: Vec3,
+    // When the ray was sent, within the camera's shutter interval. Moving
+    // objects are hit where they are at this time.
+    pub time: f64,
+}
+
+impl Ray {
+    pub fn new(origin: Point3, direction: Vec3) -> Self {
+        Ray {
+            origin,
+            direction: direction.normalize(),
+            time: 0.0,
+        }
+    }
+
+    pub fn with_time(mut self, time: f64) -> Self {
+        self.time = time;
+        self
+    }
+
+    pub fn point_at(&self, t: f64) -> Point3 {
+        self.origin + self.direction * t
+    }
+}
+
+// Objects, lights, background and media, ready to be rendered
+pub struct Scene {
+    // Finite objects live in the BVH; infinite ones (planes) are tested
+    // against every ray. Each keeps its position in the scene's object
+    // list.
+    bounded: Vec<(usize, Box<dyn Hittable>)>,
+    bvh: Bvh,
+    unbounded: Vec<(usize, Box<dyn Hittable>)>,
+    lights: Vec<Light>,
+    ambient_intensity: f64,
+    background: Background,
+    media: Vec<Medium>,
+    fog: Option<Fog>,
+    // Named materials in the order they were declared, which numbers them
+    // for the material ID AOV
+    materials: Vec<Arc<Material>>,
+}
+
+impl Scene {
+    pub fn new(
+        objects: Vec<Box<dyn Hittable>>,
+        lights: Vec<Light>,
+        ambient_intensity: f64,
+        background: Background,
+    ) -> Self {
+        let (bounded, unbounded): (Vec<_>, Vec<_>) = objects
+            .into_iter()
+            .enumerate()
+            .partition(|(_, object)| object.bounding_box().is_some());
+        let bounds: Vec<Aabb> = bounded
+            .iter()
+            .filter_map(|(_, object)| object.bounding_box())
+            .collect();
+
+        Scene {
+            bvh: Bvh::build(&bounds),
+            bounded,
+            unbounded,
+            lights,
+            ambient_intensity,
+            background,
+            media: Vec::new(),
+            fog: None,
+            materials: Vec::new(),
+        }
+    }
+
+    pub fn with_media(mut self, media: Vec<Medium>) -> Self {
+        self.media = media;
+        self
+    }
+
+    pub fn with_fog(mut self, fog: Fog) -> Self {
+        self.fog = Some(fog);
+        self
+    }
+
+    pub fn with_materials(mut self, materials: Vec<Arc<Material>>) -> Self {
+        self.materials = materials;
+        self
+    }
+
+    // Position of `material` among the named materials. Unnamed ones, such
+    // as a shape's own `color`, all share the number after the last.
+    pub(crate) fn material_id(&self, material: &Material) -> usize {
+        self.materials
+            .iter()
+            .position(|named| named.is_or_wraps(material))
+            .unwrap_or(self.materials.len())
+    }
+
+    // Find the closest intersection in (t_min, t_max)
+    pub fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<Hit<'_>> {
+        self.hit_object(ray, t_min, t_max).map(|(_, hit)| hit)
+    }
+
+    // The closest intersection and the position in the scene's object list
+    // of the object that was hit
+    pub fn hit_object(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<(usize, Hit<'_>)> {
+        progress::count_ray();
+        let mut closest = self.bvh.closest(ray, t_min, t_max, |i, limit| {
+            let (index, object) = &self.bounded[i];
+            object.intersect(ray, t_min, limit).map(|hit| (hit.t, (*index, hit)))
+        });
+        for (index, object) in &self.unbounded {
+            let limit = closest.map_or(t_max, |(_, hit)| hit.t);
+            if let Some(hit) = object.intersect(ray, t_min, limit) {
+                closest = Some((*index, hit));
+            }
+        }
+        let (index, mut hit) = closest?;
+        hit.material.prepare(&mut hit);
+        Some((index, hit))
+    }
+
+    // Whether anything blocks the ray before `t_max`. Stops at the first hit.
+    pub fn occluded(&self, ray: &Ray, t_max: f64) -> bool {
+        progress::count_ray();
+        self.unbounded
+            .iter()
+            .any(|(_, object)| object.intersect(ray, 0.0, t_max).is_some())
+            || self.bvh.any(ray, 0.0, t_max, |i| {
+                self.bounded[i].1.intersect(ray, 0.0, t_max).is_some()
+            })
+    }
+
+    // Color seen along `ray`, following at most `depth` reflection or
+    // refraction bounces
+    pub(crate) fn trace(&self, ray: &Ray, depth: u32, rng: &mut Rng) -> Color {
+        let hit = self.hit(ray, 0.0, f64::INFINITY);
+        if let Some((t, medium)) = self.medium_event(ray, hit.map_or(f64::INFINITY, |hit| hit.t), rng) {
+            let ambient = medium.albedo * self.ambient_intensity;
+            let color = ambient + self.medium_light(ray, ray.point_at(t), medium, rng);
+            return self.fogged(color, t);
+        }
+        let Some(hit) = hit else {
+            return self.background.color(ray.direction);
+        };
+
+        let color = match hit.material.scatter(ray, &hit, rng) {
+            Scatter::Diffuse(shading) => {
+                shading.ambient(self.ambient_intensity) + self.direct_light(ray, &hit, &shading, rng)
+            }
+            Scatter::Rays(_) if depth == 0 => Color::black(),
+            Scatter::Rays(rays) => rays
+                .iter()
+                .fold(Color::black(), |color, (ray, weight)| {
+                    color + self.trace(ray, depth - 1, rng) * *weight
+                }),
+        };
+        self.fogged(color, hit.t)
+    }
+
+    // `color` as seen through `distance` of fog
+    pub(crate) fn fogged(&self, color: Color, distance: f64) -> Color {
+        match self.fog {
+            Some(fog) => color.lerp(&fog.color, 1.0 - fog.transmittance(distance)),
+            None => color,
+        }
+    }
+
+    // Where `ray` first scatters in any of the media before `t_max`, and
+    // in which medium
+    pub(crate) fn medium_event(&self, ray: &Ray, t_max: f64, rng: &mut Rng) -> Option<(f64, &Medium)> {
+        let mut nearest: Option<(f64, &Medium)> = None;
+        for medium in &self.media {
+            let limit = nearest.map_or(t_max, |(t, _)| t);
+            if let Some(t) = medium.sample_distance(ray, limit, rng) {
+                nearest = Some((t, medium));
+            }
+        }
+        nearest
+    }
+
+    // Fraction of light that gets through the media along `ray` up to
+    // `t_max`
+    fn transmittance(&self, ray: &Ray, t_max: f64) -> f64 {
+        self.media.iter().map(|medium| medium.transmittance(ray, t_max)).product()
+    }
+
+    // Light reflected back along `ray` from every light that is not in
+    // shadow. Area lights are sampled several times, giving soft shadow
+    // edges.
+    pub(crate) fn direct_light(&self, ray: &Ray, hit: &Hit, shading: &Shading, rng: &mut Rng) -> Color {
+        let to_eye = -ray.direction;
+        self.gather_light(hit.point, Some(hit.normal), ray.time, rng, |sample| {
+            shading.evaluate(hit.normal, sample.direction, to_eye, sample.radiance)
+        })
+    }
+
+    // Light scattered back along `ray` by `medium` at `point`. Light
+    // radiance is scaled as in `Shading::evaluate`, which leaves out the
+    // 1/π of a Lambertian surface, so the phase function is scaled to match.
+    pub(crate) fn medium_light(&self, ray: &Ray, point: Point3, medium: &Medium, rng: &mut Rng) -> Color {
+        let scattered = self.gather_light(point, None, ray.time, rng, |sample| {
+            sample.radiance * (PI * medium.phase.evaluate(ray.direction.dot(&sample.direction)))
+        });
+        medium.albedo * scattered
+    }
+
+    // Fraction of the light arriving at a surface that is blocked by
+    // objects or dimmed by media, weighted by how bright each light sample
+    // would be. Surfaces facing away from every light count as lit.
+    pub(crate) fn shadow(&self, hit: &Hit, time: f64, rng: &mut Rng) -> f64 {
+        let mut blocked = 0.0;
+        let mut total = 0.0;
+        for light in &self.lights {
+            for _ in 0..light.samples {
+                let Some(sample) = light.sample(hit.point, rng) else {
+                    continue;
+                };
+                let cos = hit.normal.dot(&sample.direction);
+                if cos <= 0.0 {
+                    continue;
+                }
+                let weight = sample.radiance.average() * cos / light.samples as f64;
+                let shadow_ray = Ray::new(hit.point + hit.normal * 0.001, sample.direction).with_time(time);
+                let visible = if self.occluded(&shadow_ray, sample.distance) {
+                    0.0
+                } else {
+                    self.transmittance(&shadow_ray, sample.distance)
+                };
+                blocked += weight * (1.0 - visible);
+                total += weight;
+            }
+        }
+        if total > 0.0 {
+            blocked / total
+        } else {
+            0.0
+        }
+    }
+
+    // Sum of `shade` over the lights' samples that are not in shadow,
+    // averaged per light. Surfaces pass their `normal`, which skips lights
+    // behind them and lifts shadow rays off the surface.
+    fn gather_light(
+        &self,
+        point: Point3,
+        normal: Option<Vec3>,
+        time: f64,
+        rng: &mut Rng,
+        shade: impl Fn(&LightSample) -> Color,
+    ) -> Color {
+        let mut light_sum = Color::black();
+
+        for light in &self.lights {
+            let mut contribution = Color::black();
+            for _ in 0..light.samples {
+                let Some(sample) = light.sample(point, rng) else {
+                    continue;
+                };
+                let origin = match normal {
+                    Some(normal) if normal.dot(&sample.direction) <= 0.0 => continue,
+                    Some(normal) => point + normal * 0.001,
+                    None => point,
+                };
+
+                // Check for shadows, ignoring anything behind the light.
+                // Media in the way dim the light.
+                let shadow_ray = Ray::new(origin, sample.direction).with_time(time);
+                if !self.occluded(&shadow_ray, sample.distance) {
+                    contribution += shade(&sample) * self.transmittance(&shadow_ray, sample.distance);
+                }
+            }
+            light_sum += contribution / light.samples as f64;
+        }
+
+        light_sum
+    }
+}
@@ -0,0 +1,156 @@
+// Light sources
+
+use crate::color::Color;
+use crate::environment::EnvironmentMap;
+use crate::rng::Rng;
+use crate::vec3::{Point3, Vec3};
+use std::f64::consts::PI;
+use std::sync::Arc;
+
+#[derive(Debug, Clone)]
+pub enum LightKind {
+    // Light from a single point in every direction
+    Point { position: Point3 },
+    // Parallel light from infinitely far away (like the sun), travelling
+    // along `direction`
+    Directional { direction: Vec3 },
+    // Point light restricted to a cone around `direction`. Both angles are
+    // measured from the cone axis; light fades out between them.
+    Spot {
+        position: Point3,
+        direction: Vec3,
+        cos_inner: f64,
+        cos_outer: f64,
+    },
+    // Parallelogram spanned by two edges from `corner`, emitting towards
+    // the side `edge_u × edge_v` points to
+    Rect { corner: Point3, edge_u: Vec3, edge_v: Vec3 },
+    // Glowing ball
+    Sphere { center: Point3, radius: f64 },
+    // Light arriving from every direction, as seen in the background
+    Environment(Arc<EnvironmentMap>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Falloff {
+    // Same brightness at any distance
+    None,
+    // Physically based: brightness drops with the square of the distance
+    InverseSquare,
+}
+
+#[derive(Debug, Clone)]
+pub struct Light {
+    pub kind: LightKind,
+    // Color multiplied by intensity
+    pub radiance: Color,
+    pub falloff: Falloff,
+    // Shadow rays per shading point. Area lights need several to produce
+    // soft shadows; point-like lights always use one.
+    pub samples: u32,
+}
+
+// One point on a light as seen from a shading point
+pub struct LightSample {
+    // Unit vector from the shading point towards the light
+    pub direction: Vec3,
+    // Distance to the sampled point (infinite for directional lights)
+    pub distance: f64,
+    // Light arriving at the shading point if nothing blocks it
+    pub radiance: Color,
+}
+
+impl Light {
+    pub fn new(kind: LightKind, radiance: Color, falloff: Falloff, samples: u32) -> Self {
+        let samples = match kind {
+            LightKind::Rect { .. } | LightKind::Sphere { .. } | LightKind::Environment(_) => samples.max(1),
+            _ => 1,
+        };
+        Light {
+            kind,
+            radiance,
+            falloff,
+            samples,
+        }
+    }
+
+    // Picks a point on the light (randomly, for area lights) and returns
+    // how it illuminates `point`. `None` if it does not reach it at all.
+    pub fn sample(&self, point: Point3, rng: &mut Rng) -> Option<LightSample> {
+        let (position, emitter_cos) = match self.kind {
+            LightKind::Environment(ref map) => {
+                // Dividing by pi matches the diffuse response to the other
+                // lights, whose `radiance` is an irradiance
+                let (direction, radiance, pdf) = map.sample(rng)?;
+                return Some(LightSample {
+                    direction,
+                    distance: f64::INFINITY,
+                    radiance: radiance * self.radiance / (PI * pdf),
+                });
+            }
+            LightKind::Directional { direction } => {
+                return Some(LightSample {
+                    direction: (-direction).normalize(),
+                    distance: f64::INFINITY,
+                    radiance: self.radiance,
+                });
+            }
+            LightKind::Point { position } => (position, 1.0),
+            LightKind::Spot {
+                position,
+                direction,
+                cos_inner,
+                cos_outer,
+            } => {
+                let cos_angle = (point - position).normalize().dot(&direction);
+                if cos_angle <= cos_outer {
+                    return None;
+                }
+                (position, smoothstep(cos_outer, cos_inner, cos_angle))
+            }
+            LightKind::Rect { corner, edge_u, edge_v } => {
+                let position = corner + edge_u * rng.next_f64() + edge_v * rng.next_f64();
+                let normal = edge_u.cross(&edge_v).normalize();
+                let cos = normal.dot(&(point - position).normalize());
+                if cos <= 0.0 {
+                    return None;
+                }
+                (position, cos)
+            }
+            LightKind::Sphere { center, radius } => {
+                // Uniform point on the sphere, mirrored onto the half that
+                // faces the shading point
+                let mut normal = rng.in_unit_sphere().normalize();
+                let to_point = point - center;
+                if normal.dot(&to_point) < 0.0 {
+                    normal = -normal;
+                }
+                if to_point.length() <= radius {
+                    return None;
+                }
+                (center + normal * radius, normal.dot(&to_point.normalize()))
+            }
+        };
+
+        let to_light = position - point;
+        let distance = to_light.length();
+        let attenuation = match self.falloff {
+            Falloff::None => 1.0,
+            Falloff::InverseSquare => 1.0 / (distance * distance).max(1e-8),
+        };
+        Some(LightSample {
+            direction: to_light / distance,
+            distance,
+            radiance: self.radiance * (emitter_cos * attenuation),
+        })
+    }
+}
+
+// Smooth 0 to 1 transition as `x` goes from `edge0` to `edge1`
+fn smoothstep(edge0: f64, edge1: f64, x: f64) -> f64 {
+    if edge1 <= edge0 {
+        return if x >= edge1 { 1.0 } else { 0.0 };
+    }
+    let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
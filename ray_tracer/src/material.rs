@@ -0,0 +1,304 @@
+// Surface materials
+
+use crate::color::Color;
+use crate::hittable::Hit;
+use crate::normal_map::NormalMap;
+use crate::rng::Rng;
+use crate::texture::Texture;
+use crate::vec3::Vec3;
+use crate::Ray;
+
+#[derive(Debug, Clone)]
+pub enum Material {
+    // Matte surface lit directly by the scene's lights
+    Lambertian { albedo: Texture },
+    // Lambertian plus a Blinn-Phong highlight, with separate ambient,
+    // diffuse and specular coefficients
+    Phong {
+        albedo: Texture,
+        specular: Color,
+        shininess: f64,
+        ka: f64,
+        kd: f64,
+        ks: f64,
+    },
+    // Mirror reflection, blurred by `roughness` (0 = perfect mirror)
+    Metal { albedo: Texture, roughness: f64 },
+    // Clear refractive material such as glass or water
+    Dielectric { ior: f64 },
+    // Another material with its shading normals bent by a normal or bump
+    // map
+    Mapped { base: Box<Material>, map: NormalMap },
+}
+
+// What to do at a hit
+pub enum Scatter {
+    // Shade with the scene's lights
+    Diffuse(Shading),
+    // Secondary rays, each with the weight its traced color is multiplied by
+    Rays(Vec<(Ray, Color)>),
+}
+
+// Local shading model for surfaces lit by the scene's lights
+#[derive(Debug, Clone, Copy)]
+pub struct Shading {
+    pub albedo: Color,
+    pub specular: Color,
+    pub shininess: f64,
+    pub ka: f64,
+    pub kd: f64,
+    pub ks: f64,
+}
+
+impl Shading {
+    pub fn lambertian(albedo: Color) -> Self {
+        Shading {
+            albedo,
+            specular: Color::black(),
+            shininess: 1.0,
+            ka: 1.0,
+            kd: 1.0,
+            ks: 0.0,
+        }
+    }
+
+    // Color reflected towards `to_eye` from light of `radiance` arriving
+    // along `to_light` (both unit vectors pointing away from the surface)
+    pub fn evaluate(&self, normal: Vec3, to_light: Vec3, to_eye: Vec3, radiance: Color) -> Color {
+        let cos = normal.dot(&to_light);
+        if cos <= 0.0 {
+            return Color::black();
+        }
+        let mut reflected = self.albedo * (self.kd * cos);
+        if self.ks > 0.0 {
+            let half = (to_light + to_eye).normalize();
+            let highlight = normal.dot(&half).max(0.0).powf(self.shininess);
+            reflected += self.specular * (self.ks * highlight);
+        }
+        reflected * radiance
+    }
+
+    pub fn ambient(&self, intensity: f64) -> Color {
+        self.albedo * (self.ka * intensity)
+    }
+}
+
+impl Material {
+    // Applies any normal map to `hit` and points it at the underlying
+    // material. Called once per hit, before shading.
+    pub fn prepare<'a>(&'a self, hit: &mut Hit<'a>) {
+        if let Material::Mapped { base, map } = self {
+            map.apply(hit);
+            hit.material = base;
+            base.prepare(hit);
+        }
+    }
+
+    // Whether `other` is this material or, for normal and bump mapped
+    // ones, the base that `prepare` swaps in
+    pub fn is_or_wraps(&self, other: &Material) -> bool {
+        match self {
+            Material::Mapped { base, .. } => std::ptr::eq(self, other) || base.is_or_wraps(other),
+            _ => std::ptr::eq(self, other),
+        }
+    }
+
+    // Unlit surface color at `hit`, for the albedo AOV. Glass counts as
+    // white.
+    pub fn albedo(&self, hit: &Hit) -> Color {
+        match self {
+            Material::Lambertian { albedo } | Material::Phong { albedo, .. } | Material::Metal { albedo, .. } => {
+                albedo.value(hit.uv, hit.point)
+            }
+            Material::Dielectric { .. } => Color::white(),
+            Material::Mapped { base, .. } => base.albedo(hit),
+        }
+    }
+
+    pub fn scatter(&self, ray: &Ray, hit: &Hit, rng: &mut Rng) -> Scatter {
+        match *self {
+            Material::Lambertian { ref albedo } => {
+                Scatter::Diffuse(Shading::lambertian(albedo.value(hit.uv, hit.point)))
+            }
+            Material::Phong {
+                ref albedo,
+                specular,
+                shininess,
+                ka,
+                kd,
+                ks,
+            } => Scatter::Diffuse(Shading {
+                albedo: albedo.value(hit.uv, hit.point),
+                specular,
+                shininess,
+                ka,
+                kd,
+                ks,
+            }),
+            Material::Metal { ref albedo, roughness } => {
+                let reflected = ray.direction.reflect(&hit.normal) + rng.in_unit_sphere() * roughness;
+                // Roughness can push the ray below the surface; absorb it
+                if reflected.dot(&hit.normal) <= 0.0 {
+                    return Scatter::Rays(Vec::new());
+                }
+                Scatter::Rays(vec![(offset_ray(ray, hit, reflected), albedo.value(hit.uv, hit.point))])
+            }
+            Material::Dielectric { ior } => {
+                let eta = if hit.front_face { 1.0 / ior } else { ior };
+                let cos_theta = (-ray.direction).dot(&hit.normal).min(1.0);
+                let reflected = offset_ray(ray, hit, ray.direction.reflect(&hit.normal));
+                let white = Color::white();
+
+                match ray.direction.refract(&hit.normal, eta) {
+                    // Total internal reflection
+                    None => Scatter::Rays(vec![(reflected, white)]),
+                    Some(refracted) => {
+                        let fresnel = schlick(cos_theta, eta);
+                        Scatter::Rays(vec![
+                            (reflected, white * fresnel),
+                            (offset_ray(ray, hit, refracted), white * (1.0 - fresnel)),
+                        ])
+                    }
+                }
+            }
+            Material::Mapped { ref base, .. } => base.scatter(ray, hit, rng),
+        }
+    }
+}
+
+// Starts a secondary ray slightly off the surface, on the side it leaves
+// towards, so it does not immediately hit the surface it came from. It
+// keeps the time of the incoming `ray`.
+fn offset_ray(ray: &Ray, hit: &Hit, direction: Vec3) -> Ray {
+    let side = if direction.dot(&hit.normal) > 0.0 { 0.001 } else { -0.001 };
+    Ray::new(hit.point + hit.normal * side, direction).with_time(ray.time)
+}
+
+// Schlick's approximation of the Fresnel reflectance
+fn schlick(cos_theta: f64, eta: f64) -> f64 {
+    let r0 = ((1.0 - eta) / (1.0 + eta)).powi(2);
+    r0 + (1.0 - r0) * (1.0 - cos_theta).powi(5)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vec3::Point3;
+
+    // Rays scattered where a ray travelling along `direction` meets the
+    // z = 0 plane at the origin, whose outward side faces +z
+    fn scatter(material: &Material, direction: Vec3) -> Vec<(Ray, Color)> {
+        let direction = direction.normalize();
+        let ray = Ray::new(Point3::origin() - direction, direction).with_time(0.25);
+        let hit = Hit::new(&ray, 1.0, Vec3::new(0.0, 0.0, 1.0), (0.0, 0.0), material);
+        match material.scatter(&ray, &hit, &mut Rng::new(1)) {
+            Scatter::Rays(rays) => rays,
+            Scatter::Diffuse(_) => panic!("expected secondary rays"),
+        }
+    }
+
+    fn assert_close(a: Vec3, b: Vec3) {
+        assert!((a - b).length() < 1e-9, "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn matte_surfaces_are_shaded_by_the_lights() {
+        let material = Material::Lambertian {
+            albedo: Texture::Solid(Color::new(0.5, 0.25, 1.0)),
+        };
+        let ray = Ray::new(Point3::new(0.0, 0.0, 1.0), Vec3::new(0.0, 0.0, -1.0));
+        let hit = Hit::new(&ray, 1.0, Vec3::new(0.0, 0.0, 1.0), (0.0, 0.0), &material);
+        let Scatter::Diffuse(shading) = material.scatter(&ray, &hit, &mut Rng::new(1)) else {
+            panic!("expected shading");
+        };
+        assert_eq!(shading.albedo, Color::new(0.5, 0.25, 1.0));
+
+        // Lambert's cosine law, and nothing from below the surface
+        let normal = Vec3::new(0.0, 0.0, 1.0);
+        let to_light = Vec3::new(0.0, 0.6, 0.8);
+        let lit = shading.evaluate(normal, to_light, normal, Color::white());
+        assert!((lit.r - 0.4).abs() < 1e-12);
+        assert_eq!(shading.evaluate(normal, -to_light, normal, Color::white()), Color::black());
+    }
+
+    #[test]
+    fn metal_mirrors_rays() {
+        let material = Material::Metal {
+            albedo: Texture::Solid(Color::new(0.9, 0.6, 0.3)),
+            roughness: 0.0,
+        };
+        let rays = scatter(&material, Vec3::new(1.0, 0.0, -1.0));
+        assert_eq!(rays.len(), 1);
+        let (reflected, weight) = rays[0];
+        assert_close(reflected.direction, Vec3::new(1.0, 0.0, 1.0).normalize());
+        assert_eq!(weight, Color::new(0.9, 0.6, 0.3));
+        // Leaves from just above the surface, at the same time
+        assert!(reflected.origin.z > 0.0 && reflected.origin.z < 0.01);
+        assert_eq!(reflected.time, 0.25);
+    }
+
+    #[test]
+    fn rough_metal_blurs_around_the_mirror_direction() {
+        let material = Material::Metal {
+            albedo: Texture::Solid(Color::white()),
+            roughness: 0.3,
+        };
+        let mirror = Vec3::new(0.0, 0.0, 1.0);
+        let ray = Ray::new(Point3::new(0.0, 0.0, 1.0), -mirror);
+        let hit = Hit::new(&ray, 1.0, mirror, (0.0, 0.0), &material);
+        let mut rng = Rng::new(2);
+        let mut spread: f64 = 0.0;
+        for _ in 0..100 {
+            let Scatter::Rays(rays) = material.scatter(&ray, &hit, &mut rng) else {
+                panic!("expected secondary rays");
+            };
+            // Pushed at most `roughness` off the unit mirror direction
+            let cos = rays[0].0.direction.dot(&mirror);
+            assert!(cos >= (1.0f64 - 0.3 * 0.3).sqrt() - 1e-9);
+            spread = spread.max(1.0 - cos);
+        }
+        assert!(spread > 0.0);
+    }
+
+    #[test]
+    fn glass_splits_rays_by_fresnel_and_snell() {
+        let glass = Material::Dielectric { ior: 1.5 };
+
+        // Head on, 4% is reflected and the rest passes straight through
+        let rays = scatter(&glass, Vec3::new(0.0, 0.0, -1.0));
+        assert_eq!(rays.len(), 2);
+        let (reflected, reflectance) = rays[0];
+        let (refracted, transmittance) = rays[1];
+        assert_close(reflected.direction, Vec3::new(0.0, 0.0, 1.0));
+        assert_close(refracted.direction, Vec3::new(0.0, 0.0, -1.0));
+        assert!((reflectance.r - 0.04).abs() < 1e-12);
+        assert!((reflectance.r + transmittance.r - 1.0).abs() < 1e-12);
+        assert!(refracted.origin.z < 0.0);
+
+        // At 45 degrees the ray bends towards the normal
+        let rays = scatter(&glass, Vec3::new(1.0, 0.0, -1.0));
+        let refracted = rays[1].0.direction;
+        assert!((refracted.x - 0.5f64.sqrt() / 1.5).abs() < 1e-9);
+        assert!(refracted.z < 0.0);
+    }
+
+    #[test]
+    fn glass_reflects_everything_past_the_critical_angle() {
+        // Leaving the glass at 60 degrees from the normal, past the
+        // critical angle of about 42 degrees
+        let glass = Material::Dielectric { ior: 1.5 };
+        let direction = Vec3::new(60f64.to_radians().sin(), 0.0, 60f64.to_radians().cos());
+        let rays = scatter(&glass, direction);
+        assert_eq!(rays.len(), 1);
+        let (reflected, weight) = rays[0];
+        assert_close(reflected.direction, Vec3::new(direction.x, 0.0, -direction.z));
+        assert_eq!(weight, Color::white());
+        // Stays inside
+        assert!(reflected.origin.z < 0.0);
+
+        // Just under the critical angle some light still gets out
+        let critical = (1.0f64 / 1.5).asin() - 0.01;
+        let rays = scatter(&glass, Vec3::new(critical.sin(), 0.0, critical.cos()));
+        assert_eq!(rays.len(), 2);
+    }
+}
@@ -0,0 +1,232 @@
+// Participating media and distance fog
+//
+// A medium fills the inside of a closed boundary shape with particles of
+// constant density, such as smoke, mist or milky liquid. Rays passing
+// through may scatter at a random distance inside it, and light reaching a
+// point through a medium is dimmed by it, which gives soft volumes and
+// light shafts. Fog is the cheap alternative: everything fades towards one
+// color with distance.
+
+use crate::color::Color;
+use crate::hittable::Hittable;
+use crate::rng::Rng;
+use crate::sampling::orthonormal_basis;
+use crate::vec3::Vec3;
+use crate::Ray;
+use std::f64::consts::PI;
+
+// How scattered light is spread over directions
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Phase {
+    // The same in every direction
+    Isotropic,
+    // Henyey-Greenstein: `g` > 0 scatters mostly forwards (haze, clouds),
+    // `g` < 0 mostly backwards; 0 is isotropic
+    HenyeyGreenstein { g: f64 },
+}
+
+impl Phase {
+    // Density of scattering by an angle with cosine `cos_theta` from the
+    // direction of travel, over the sphere of directions
+    pub fn evaluate(&self, cos_theta: f64) -> f64 {
+        match *self {
+            Phase::Isotropic => 1.0 / (4.0 * PI),
+            Phase::HenyeyGreenstein { g } => {
+                let denom = 1.0 + g * g - 2.0 * g * cos_theta;
+                (1.0 - g * g) / (4.0 * PI * denom * denom.sqrt())
+            }
+        }
+    }
+
+    // New direction of travel after scattering, with probability
+    // proportional to `evaluate`
+    pub fn sample(&self, direction: Vec3, rng: &mut Rng) -> Vec3 {
+        let cos_theta = match *self {
+            Phase::HenyeyGreenstein { g } if g.abs() > 1e-3 => {
+                let s = (1.0 - g * g) / (1.0 - g + 2.0 * g * rng.next_f64());
+                ((1.0 + g * g - s * s) / (2.0 * g)).clamp(-1.0, 1.0)
+            }
+            _ => 1.0 - 2.0 * rng.next_f64(),
+        };
+        let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+        let phi = 2.0 * PI * rng.next_f64();
+        let (tangent, bitangent) = orthonormal_basis(direction);
+        tangent * (sin_theta * phi.cos()) + bitangent * (sin_theta * phi.sin()) + direction * cos_theta
+    }
+}
+
+pub struct Medium {
+    // Closed shape the medium fills. It is not drawn itself.
+    boundary: Box<dyn Hittable>,
+    // Chance of a ray interacting per unit distance
+    pub density: f64,
+    // Fraction of interacting light that is scattered rather than
+    // absorbed, per channel
+    pub albedo: Color,
+    pub phase: Phase,
+}
+
+impl Medium {
+    pub fn new(boundary: Box<dyn Hittable>, density: f64, albedo: Color, phase: Phase) -> Self {
+        Medium {
+            boundary,
+            density,
+            albedo,
+            phase,
+        }
+    }
+
+    // Where along `ray`, before `t_max`, it scatters in this medium, if it
+    // does. The distance travelled inside follows an exponential
+    // distribution.
+    pub fn sample_distance(&self, ray: &Ray, t_max: f64, rng: &mut Rng) -> Option<f64> {
+        let mut remaining = -(1.0 - rng.next_f64()).ln() / self.density;
+        for (start, end) in self.inside(ray, t_max) {
+            if remaining < end - start {
+                return Some(start + remaining);
+            }
+            remaining -= end - start;
+        }
+        None
+    }
+
+    // Fraction of light that passes through the medium along `ray` up to
+    // `t_max`
+    pub fn transmittance(&self, ray: &Ray, t_max: f64) -> f64 {
+        let length: f64 = self.inside(ray, t_max).iter().map(|(start, end)| end - start).sum();
+        (-self.density * length).exp()
+    }
+
+    // Stretches of `ray` inside the boundary, cut off at `t_max`. Like CSG,
+    // each crossing of the boundary says whether the ray enters or leaves.
+    fn inside(&self, ray: &Ray, t_max: f64) -> Vec<(f64, f64)> {
+        let mut stretches = Vec::new();
+        let mut entered: Option<f64> = None;
+        let mut t = 0.0;
+        let mut first = true;
+        while let Some(hit) = self.boundary.intersect(ray, t, f64::INFINITY) {
+            // A ray that starts inside leaves at its first crossing
+            if first && !hit.front_face {
+                entered = Some(0.0);
+            }
+            first = false;
+            match (hit.front_face, entered) {
+                (true, None) => entered = Some(hit.t),
+                (false, Some(start)) => {
+                    stretches.push((start, hit.t.min(t_max)));
+                    entered = None;
+                }
+                _ => {}
+            }
+            if hit.t >= t_max {
+                break;
+            }
+            t = hit.t + 1e-9;
+        }
+        if let Some(start) = entered {
+            stretches.push((start, t_max));
+        }
+        stretches.retain(|(start, end)| start < end);
+        stretches
+    }
+}
+
+// Exponential distance fog: a surface at distance d keeps exp(-density * d)
+// of its color and takes the rest from the fog color. The background is
+// left alone, so it works best with a fog color close to the horizon's.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Fog {
+    pub color: Color,
+    pub density: f64,
+}
+
+impl Fog {
+    // Fraction of the color at `distance` that is still visible
+    pub fn transmittance(&self, distance: f64) -> f64 {
+        (-self.density * distance).exp()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::Material;
+    use crate::shapes::Sphere;
+    use crate::texture::Texture;
+    use crate::vec3::Point3;
+    use std::sync::Arc;
+
+    // Unit sphere of smoke around the origin
+    fn smoke(density: f64) -> Medium {
+        let material = Arc::new(Material::Lambertian {
+            albedo: Texture::Solid(Color::white()),
+        });
+        let boundary = Box::new(Sphere::new(Point3::origin(), 1.0, material));
+        Medium::new(boundary, density, Color::white(), Phase::Isotropic)
+    }
+
+    fn assert_close(a: f64, b: f64) {
+        assert!((a - b).abs() < 1e-9, "{} != {}", a, b);
+    }
+
+    #[test]
+    fn transmittance_decays_with_the_length_inside() {
+        let medium = smoke(0.5);
+        let ray = Ray::new(Point3::new(-3.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0));
+        // Through the whole diameter, to the center, and stopping short
+        assert_close(medium.transmittance(&ray, 10.0), (-0.5f64 * 2.0).exp());
+        assert_close(medium.transmittance(&ray, 3.0), (-0.5f64).exp());
+        assert_eq!(medium.transmittance(&ray, 1.5), 1.0);
+
+        // A chord off center, and a ray starting inside
+        let chord = Ray::new(Point3::new(-3.0, 0.6, 0.0), Vec3::new(1.0, 0.0, 0.0));
+        assert_close(medium.transmittance(&chord, 10.0), (-0.5f64 * 1.6).exp());
+        let inside = Ray::new(Point3::new(0.0, 0.0, 0.5), Vec3::new(0.0, 0.0, -1.0));
+        assert_close(medium.transmittance(&inside, 10.0), (-0.5f64 * 1.5).exp());
+
+        // Missing the medium altogether
+        let miss = Ray::new(Point3::new(-3.0, 2.0, 0.0), Vec3::new(1.0, 0.0, 0.0));
+        assert_eq!(medium.transmittance(&miss, 10.0), 1.0);
+    }
+
+    #[test]
+    fn scattering_distances_match_the_transmittance() {
+        // The chance of passing straight through is the transmittance, and
+        // every scattering point lies inside
+        let medium = smoke(0.7);
+        let ray = Ray::new(Point3::new(-3.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0));
+        let mut rng = Rng::new(1);
+        let count = 100_000;
+        let mut passed = 0;
+        for _ in 0..count {
+            match medium.sample_distance(&ray, 10.0, &mut rng) {
+                Some(t) => assert!((2.0..=4.0).contains(&t)),
+                None => passed += 1,
+            }
+        }
+        let expected = medium.transmittance(&ray, 10.0);
+        assert!((passed as f64 / count as f64 - expected).abs() < 0.01);
+    }
+
+    #[test]
+    fn henyey_greenstein_has_mean_cosine_g() {
+        let mut rng = Rng::new(2);
+        let direction = Vec3::new(0.0, 0.0, 1.0);
+        for g in [-0.6, 0.0, 0.3, 0.9] {
+            let phase = Phase::HenyeyGreenstein { g };
+            let count = 100_000;
+            let mean: f64 = (0..count).map(|_| phase.sample(direction, &mut rng).z).sum::<f64>() / count as f64;
+            assert!((mean - g).abs() < 0.01, "g = {}: {}", g, mean);
+
+            // The density integrates to 1 over the sphere
+            let steps = 10_000;
+            let integral: f64 = (0..steps)
+                .map(|i| {
+                    let cos_theta = -1.0 + 2.0 * (i as f64 + 0.5) / steps as f64;
+                    phase.evaluate(cos_theta) * 2.0 * PI * 2.0 / steps as f64
+                })
+                .sum();
+            assert!((integral - 1.0).abs() < 1e-3, "g = {}: {}", g, integral);
+        }
+    }
+}
@@ -0,0 +1,172 @@
+// Object motion over time
+//
+// A moving object has poses at keyframe times and is interpolated linearly
+// between them; two keyframes give straight-line motion. Rays carry the
+// time they were sent at, so sampling times across the camera's shutter
+// interval gives motion blur.
+
+use crate::aabb::Aabb;
+use crate::transform::Transform;
+use crate::vec3::{Point3, Vec3};
+
+// Scale, then rotation about x, y and z in turn (degrees), then translation
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Pose {
+    pub translate: Vec3,
+    pub rotate: Vec3,
+    pub scale: Vec3,
+}
+
+impl Default for Pose {
+    fn default() -> Self {
+        Pose {
+            translate: Vec3::zero(),
+            rotate: Vec3::zero(),
+            scale: Vec3::new(1.0, 1.0, 1.0),
+        }
+    }
+}
+
+impl Pose {
+    pub fn transform(&self) -> Transform {
+        Transform::scale(self.scale)
+            .then(&Transform::rotate_x(self.rotate.x))
+            .then(&Transform::rotate_y(self.rotate.y))
+            .then(&Transform::rotate_z(self.rotate.z))
+            .then(&Transform::translate(self.translate))
+    }
+
+    // Angles are interpolated as numbers, so a key from 0 to 720 degrees
+    // spins twice
+    pub fn lerp(&self, other: &Pose, t: f64) -> Pose {
+        Pose {
+            translate: self.translate.lerp(&other.translate, t),
+            rotate: self.rotate.lerp(&other.rotate, t),
+            scale: self.scale.lerp(&other.scale, t),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Motion {
+    // Sorted by time
+    keys: Vec<(f64, Pose)>,
+}
+
+impl Motion {
+    // Needs at least one key. Before the first and after the last key the
+    // object holds still. Scale factors should keep their sign from one key
+    // to the next, or the shape is flattened to nothing on the way.
+    pub fn new(mut keys: Vec<(f64, Pose)>) -> Self {
+        assert!(!keys.is_empty(), "motion needs at least one key");
+        keys.sort_by(|a, b| a.0.total_cmp(&b.0));
+        Motion { keys }
+    }
+
+    pub fn pose(&self, time: f64) -> Pose {
+        let next = self.keys.partition_point(|(key_time, _)| *key_time <= time);
+        if next == 0 {
+            return self.keys[0].1;
+        }
+        if next == self.keys.len() {
+            return self.keys[next - 1].1;
+        }
+        let (t0, p0) = &self.keys[next - 1];
+        let (t1, p1) = &self.keys[next];
+        p0.lerp(p1, (time - t0) / (t1 - t0))
+    }
+
+    pub fn at(&self, time: f64) -> Transform {
+        self.pose(time).transform()
+    }
+
+    // Box enclosing `bounds` over the whole motion. Without rotation every
+    // point moves along a straight line, so the boxes at the keys cover the
+    // way between them. A rotation can sweep the box anywhere within its
+    // farthest corner's distance from the pivot, so between keys that
+    // rotate, that sphere is carried along the translation instead.
+    pub fn bounds(&self, bounds: &Aabb) -> Aabb {
+        let mut swept = self.at(self.keys[0].0).bounds(bounds);
+        for pair in self.keys.windows(2) {
+            let (from, to) = (&pair[0].1, &pair[1].1);
+            swept = swept.union(&to.transform().bounds(bounds));
+            if from.rotate == to.rotate {
+                continue;
+            }
+            // Scales keep their sign, so each factor is largest at a key
+            let largest = |axis: usize| from.scale[axis].abs().max(to.scale[axis].abs());
+            let scale = Vec3::new(largest(0), largest(1), largest(2));
+            let reach = (0..8)
+                .map(|i| {
+                    let corner = Vec3::new(
+                        if i & 1 == 0 { bounds.min.x } else { bounds.max.x },
+                        if i & 2 == 0 { bounds.min.y } else { bounds.max.y },
+                        if i & 4 == 0 { bounds.min.z } else { bounds.max.z },
+                    );
+                    (corner * scale).length()
+                })
+                .fold(0.0, f64::max);
+            let reach = Vec3::new(reach, reach, reach);
+            for translate in [from.translate, to.translate] {
+                let center = Point3::from(translate);
+                swept = swept.union(&Aabb::new(center - reach, center + reach));
+            }
+        }
+        swept
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Whether `inner` lies within `outer`, give or take rounding
+    fn encloses(outer: &Aabb, inner: &Aabb) -> bool {
+        (0..3).all(|axis| outer.min[axis] <= inner.min[axis] + 1e-9 && inner.max[axis] <= outer.max[axis] + 1e-9)
+    }
+
+    #[test]
+    fn bounds_cover_every_pose() {
+        // A long, thin box off the pivot, spun twenty times while it moves
+        // and grows
+        let object = Aabb::new(Point3::new(2.0, -0.1, -0.1), Point3::new(3.0, 0.1, 0.1));
+        let motion = Motion::new(vec![
+            (0.0, Pose::default()),
+            (
+                1.0,
+                Pose {
+                    translate: Vec3::new(1.0, 2.0, 0.0),
+                    rotate: Vec3::new(30.0, 0.0, 7200.0),
+                    scale: Vec3::new(2.0, 1.0, 1.0),
+                },
+            ),
+            (
+                2.0,
+                Pose {
+                    translate: Vec3::new(-4.0, 2.0, 1.0),
+                    rotate: Vec3::new(30.0, 0.0, 7200.0),
+                    scale: Vec3::new(0.5, 1.0, 1.0),
+                },
+            ),
+        ]);
+        let bounds = motion.bounds(&object);
+        for step in 0..=20_000 {
+            let time = step as f64 / 10_000.0;
+            let posed = motion.at(time).bounds(&object);
+            assert!(encloses(&bounds, &posed), "pose at {} sticks out", time);
+        }
+    }
+
+    #[test]
+    fn bounds_without_rotation_are_the_boxes_at_the_keys() {
+        let object = Aabb::new(Point3::new(-1.0, -1.0, -1.0), Point3::new(1.0, 1.0, 1.0));
+        let moved = Pose {
+            translate: Vec3::new(5.0, 0.0, 0.0),
+            ..Pose::default()
+        };
+        let motion = Motion::new(vec![(0.0, Pose::default()), (1.0, moved)]);
+        let bounds = motion.bounds(&object);
+        assert_eq!(bounds.min, Point3::new(-1.0, -1.0, -1.0));
+        assert_eq!(bounds.max, Point3::new(6.0, 1.0, 1.0));
+    }
+}
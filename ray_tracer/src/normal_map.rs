@@ -0,0 +1,131 @@
+// Shading normal perturbation
+//
+// Both kinds of map bend the normal within the hit's tangent frame, so
+// surfaces look detailed under lighting while the geometry stays flat.
+
+use crate::hittable::Hit;
+use crate::sampling::orthonormal_basis;
+use crate::texture::Texture;
+use crate::vec3::Vec3;
+
+// UV step for estimating the slope of a bump map
+const BUMP_DELTA: f64 = 1e-3;
+
+#[derive(Debug, Clone)]
+pub enum NormalMap {
+    // Tangent-space normals encoded as RGB (red = +u, green = +v, blue =
+    // out of the surface). `strength` scales the tilt; 1 uses the map as is.
+    Tangent { texture: Texture, strength: f64 },
+    // Height field from the texture's brightness; the normal tilts away
+    // from uphill, more steeply for larger `height`
+    Bump { texture: Texture, height: f64 },
+}
+
+impl NormalMap {
+    // Replaces `hit.normal` with the perturbed shading normal
+    pub fn apply(&self, hit: &mut Hit) {
+        // Work with the outward normal so the map looks the same from
+        // both sides of flat shapes
+        let sign = if hit.front_face { 1.0 } else { -1.0 };
+        let normal = hit.normal * sign;
+        let (tangent, bitangent) = frame(normal, hit.tangent, hit.bitangent);
+
+        let perturbed = match self {
+            NormalMap::Tangent { texture, strength } => {
+                let c = texture.value(hit.uv, hit.point);
+                let (x, y, z) = (2.0 * c.r - 1.0, 2.0 * c.g - 1.0, 2.0 * c.b - 1.0);
+                tangent * (x * strength) + bitangent * (y * strength) + normal * z.max(0.0)
+            }
+            NormalMap::Bump { texture, height } => {
+                let sample = |du: f64, dv: f64| {
+                    let uv = (hit.uv.0 + du, hit.uv.1 + dv);
+                    let point = hit.point + tangent * du + bitangent * dv;
+                    texture.value(uv, point).average()
+                };
+                let slope_u = (sample(BUMP_DELTA, 0.0) - sample(-BUMP_DELTA, 0.0)) / (2.0 * BUMP_DELTA);
+                let slope_v = (sample(0.0, BUMP_DELTA) - sample(0.0, -BUMP_DELTA)) / (2.0 * BUMP_DELTA);
+                normal - tangent * (height * slope_u) - bitangent * (height * slope_v)
+            }
+        };
+
+        if perturbed.length() > 1e-12 {
+            hit.normal = perturbed.normalize() * sign;
+        }
+    }
+}
+
+// Orthonormal frame around `normal`, keeping `tangent`'s direction and
+// `bitangent`'s side (the two may be mirrored relative to each other)
+fn frame(normal: Vec3, tangent: Vec3, bitangent: Vec3) -> (Vec3, Vec3) {
+    let t = tangent - normal * normal.dot(&tangent);
+    if t.length() < 1e-9 {
+        return orthonormal_basis(normal);
+    }
+    let t = t.normalize();
+    let b = normal.cross(&t);
+    if b.dot(&bitangent) < 0.0 {
+        (t, -b)
+    } else {
+        (t, b)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::Color;
+    use crate::material::Material;
+    use crate::texture::UvAxis;
+    use crate::vec3::Point3;
+    use crate::Ray;
+
+    // Bends the normal of a surface facing +z, with u along +x and v
+    // along +y, hit from in front or behind
+    fn bent(map: &NormalMap, from_front: bool) -> Vec3 {
+        let material = Material::Lambertian {
+            albedo: Texture::Solid(Color::white()),
+        };
+        let z = if from_front { 1.0 } else { -1.0 };
+        let ray = Ray::new(Point3::new(0.0, 0.0, z), Vec3::new(0.0, 0.0, -z));
+        let mut hit = Hit::new(&ray, 1.0, Vec3::new(0.0, 0.0, 1.0), (0.5, 0.5), &material)
+            .with_tangents(Vec3::new(1.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0));
+        map.apply(&mut hit);
+        hit.normal
+    }
+
+    fn assert_close(a: Vec3, b: Vec3) {
+        assert!((a - b).length() < 1e-6, "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn tangent_maps_tilt_along_the_surface_axes() {
+        // Half way towards +u
+        let map = NormalMap::Tangent {
+            texture: Texture::Solid(Color::new(0.75, 0.5, 1.0)),
+            strength: 1.0,
+        };
+        assert_close(bent(&map, true), Vec3::new(0.5, 0.0, 1.0).normalize());
+        // Seen from behind, the same tilt on the other side
+        assert_close(bent(&map, false), -Vec3::new(0.5, 0.0, 1.0).normalize());
+
+        let flat = NormalMap::Tangent {
+            texture: Texture::Solid(Color::new(0.75, 0.5, 1.0)),
+            strength: 0.0,
+        };
+        assert_close(bent(&flat, true), Vec3::new(0.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn bump_maps_tilt_away_from_uphill() {
+        // Height rises by 1 per unit of u
+        let map = NormalMap::Bump {
+            texture: Texture::Gradient {
+                from: Color::black(),
+                to: Color::white(),
+                axis: UvAxis::U,
+            },
+            height: 0.5,
+        };
+        assert_close(bent(&map, true), Vec3::new(-0.5, 0.0, 1.0).normalize());
+    }
+}
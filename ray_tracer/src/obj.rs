@@ -0,0 +1,213 @@
+// Wavefront OBJ loader
+//
+// Reads vertex positions (`v`), normals (`vn`), texture coordinates (`vt`)
+// and faces (`f`). Polygons with more than three vertices are split into a
+// triangle fan. Groups and material libraries are skipped.
+
+use crate::material::Material;
+use crate::shapes::{Mesh, MeshTriangle};
+use crate::vec3::{Point3, Vec3};
+use std::{fmt, fs, io, path::Path, sync::Arc};
+
+#[derive(Debug)]
+pub enum ObjError {
+    Io(io::Error),
+    Syntax { line: usize, message: String },
+}
+
+impl fmt::Display for ObjError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ObjError::Io(err) => write!(f, "{}", err),
+            ObjError::Syntax { line, message } => write!(f, "line {}: {}", line, message),
+        }
+    }
+}
+
+impl std::error::Error for ObjError {}
+
+impl From<io::Error> for ObjError {
+    fn from(err: io::Error) -> Self {
+        ObjError::Io(err)
+    }
+}
+
+pub fn load(path: &Path, material: Arc<Material>) -> Result<Mesh, ObjError> {
+    let source = fs::read_to_string(path)?;
+    parse(&source, material)
+}
+
+pub fn parse(source: &str, material: Arc<Material>) -> Result<Mesh, ObjError> {
+    let mut positions = Vec::new();
+    let mut normals = Vec::new();
+    let mut uvs = Vec::new();
+    let mut triangles = Vec::new();
+
+    for (index, line) in source.lines().enumerate() {
+        let line_number = index + 1;
+        let error = |message: String| ObjError::Syntax {
+            line: line_number,
+            message,
+        };
+
+        let line = line.split('#').next().unwrap_or("");
+        let mut fields = line.split_whitespace();
+        match fields.next() {
+            Some("v") => positions.push(Point3::from(vector(fields).map_err(error)?)),
+            Some("vn") => normals.push(vector(fields).map_err(error)?.normalize()),
+            Some("vt") => uvs.push(texture_coordinate(fields).map_err(error)?),
+            Some("f") => {
+                let corners = fields
+                    .map(|field| corner(field, positions.len(), uvs.len(), normals.len()))
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(error)?;
+                if corners.len() < 3 {
+                    return Err(error(format!(
+                        "a face needs at least 3 vertices, found {}",
+                        corners.len()
+                    )));
+                }
+
+                // Fan around the first corner
+                for i in 1..corners.len() - 1 {
+                    let [a, b, c] = [corners[0], corners[i], corners[i + 1]];
+                    triangles.push(MeshTriangle {
+                        positions: [a.position, b.position, c.position],
+                        normals: all([a.normal, b.normal, c.normal]),
+                        uvs: all([a.uv, b.uv, c.uv]),
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+
+    if triangles.is_empty() {
+        return Err(ObjError::Syntax {
+            line: source.lines().count(),
+            message: "no faces found".to_string(),
+        });
+    }
+    Ok(Mesh::new(positions, normals, uvs, triangles, material))
+}
+
+// Indices of one face corner
+#[derive(Clone, Copy)]
+struct Corner {
+    position: usize,
+    uv: Option<usize>,
+    normal: Option<usize>,
+}
+
+// Per-vertex attributes are only used when every corner has them
+fn all(indices: [Option<usize>; 3]) -> Option<[usize; 3]> {
+    match indices {
+        [Some(a), Some(b), Some(c)] => Some([a, b, c]),
+        _ => None,
+    }
+}
+
+// Parses the `x y z` of a `v` or `vn` line. A trailing `w` is ignored.
+fn vector<'a>(fields: impl Iterator<Item = &'a str>) -> Result<Vec3, String> {
+    let values = fields
+        .take(3)
+        .map(|field| {
+            field
+                .parse::<f64>()
+                .map_err(|_| format!("expected a number, found `{}`", field))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    if values.len() != 3 {
+        return Err(format!("expected 3 coordinates, found {}", values.len()));
+    }
+    Ok(Vec3::new(values[0], values[1], values[2]))
+}
+
+// Parses the `u v` of a `vt` line. `v` defaults to 0 and a trailing `w`
+// is ignored.
+fn texture_coordinate<'a>(mut fields: impl Iterator<Item = &'a str>) -> Result<(f64, f64), String> {
+    let mut next = |required: bool| match fields.next() {
+        Some(field) => field
+            .parse::<f64>()
+            .map_err(|_| format!("expected a number, found `{}`", field)),
+        None if required => Err("expected a texture coordinate".to_string()),
+        None => Ok(0.0),
+    };
+    Ok((next(true)?, next(false)?))
+}
+
+// Parses one face corner (`v`, `v/vt`, `v//vn` or `v/vt/vn`) into
+// zero-based indices.
+fn corner(field: &str, position_count: usize, uv_count: usize, normal_count: usize) -> Result<Corner, String> {
+    let mut parts = field.split('/');
+    let position = index(parts.next().unwrap_or(""), position_count, "vertex")?;
+    let mut optional = |count: usize, kind: &str| match parts.next() {
+        Some(part) if !part.is_empty() => index(part, count, kind).map(Some),
+        _ => Ok(None),
+    };
+    let uv = optional(uv_count, "texture coordinate")?;
+    let normal = optional(normal_count, "normal")?;
+    Ok(Corner { position, uv, normal })
+}
+
+// OBJ indices are one-based; negative indices count back from the most
+// recently defined element.
+fn index(field: &str, count: usize, kind: &str) -> Result<usize, String> {
+    let value: i64 = field
+        .parse()
+        .map_err(|_| format!("invalid {} index `{}`", kind, field))?;
+    let resolved = if value < 0 { count as i64 + value } else { value - 1 };
+    if value == 0 || resolved < 0 || resolved >= count as i64 {
+        return Err(format!("{} index {} is out of range", kind, value));
+    }
+    Ok(resolved as usize)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::Color;
+    use crate::hittable::Hittable;
+    use crate::texture::Texture;
+    use crate::Ray;
+
+    fn mesh(source: &str) -> Mesh {
+        let material = Arc::new(Material::Lambertian {
+            albedo: Texture::Solid(Color::white()),
+        });
+        parse(source, material).unwrap()
+    }
+
+    // Whether a ray straight down the z axis through (x, y) hits the mesh
+    fn covers(mesh: &Mesh, x: f64, y: f64) -> bool {
+        let ray = Ray::new(Point3::new(x, y, 1.0), Vec3::new(0.0, 0.0, -1.0));
+        mesh.intersect(&ray, 0.0, f64::INFINITY).is_some()
+    }
+
+    #[test]
+    fn fans_polygons_into_triangles() {
+        // A pentagon: a unit square with a peak on top
+        let pentagon = mesh("v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0.5 1.5 0\nv 0 1 0\nf 1 2 3 4 5\n");
+        for (x, y) in [(0.1, 0.1), (0.9, 0.1), (0.9, 0.9), (0.1, 0.9), (0.5, 1.4)] {
+            assert!(covers(&pentagon, x, y), "({}, {}) is not covered", x, y);
+        }
+        for (x, y) in [(1.1, 0.5), (0.1, 1.4), (0.5, -0.1)] {
+            assert!(!covers(&pentagon, x, y), "({}, {}) is covered", x, y);
+        }
+    }
+
+    #[test]
+    fn resolves_negative_indices_from_the_latest_vertex() {
+        // Each face refers to the three vertices just before it
+        let source = "v 0 0 0\nv 1 0 0\nv 0 1 0\nf -3 -2 -1\nv 5 0 0\nv 6 0 0\nv 5 1 0\nf -3 -2 -1\n";
+        let triangles = mesh(source);
+        assert!(covers(&triangles, 0.2, 0.2));
+        assert!(covers(&triangles, 5.2, 0.2));
+        assert!(!covers(&triangles, 2.5, 0.2));
+
+        assert_eq!(index("-1", 4, "vertex"), Ok(3));
+        assert_eq!(index("1", 4, "vertex"), Ok(0));
+        assert!(index("-5", 4, "vertex").is_err());
+        assert!(index("0", 4, "vertex").is_err());
+    }
+}
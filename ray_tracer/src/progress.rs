@@ -0,0 +1,137 @@
+// Render progress reporting
+//
+// Workers report finished tiles; the reporter prints the percentage done,
+// time elapsed and remaining, and ray throughput to stderr, at most a few
+// times a second so threads do not fight over the terminal.
+
+use std::cell::Cell;
+use std::io::{self, IsTerminal, Write};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+thread_local! {
+    // Rays traced on this thread since the last `take_ray_count`
+    static RAYS: Cell<u64> = const { Cell::new(0) };
+}
+
+// Counts one ray cast into the scene (camera, bounce or shadow ray)
+pub fn count_ray() {
+    RAYS.with(|rays| rays.set(rays.get() + 1));
+}
+
+// Returns and resets this thread's ray count
+pub fn take_ray_count() -> u64 {
+    RAYS.with(|rays| rays.replace(0))
+}
+
+pub struct Progress {
+    // Camera samples in the whole render
+    total: u64,
+    done: AtomicU64,
+    rays: AtomicU64,
+    start: Instant,
+    // When the last line was printed; also serializes printing
+    last_report: Mutex<Instant>,
+    interactive: bool,
+}
+
+impl Progress {
+    pub fn new(total: u64) -> Self {
+        let start = Instant::now();
+        Progress {
+            total: total.max(1),
+            done: AtomicU64::new(0),
+            rays: AtomicU64::new(0),
+            start,
+            last_report: Mutex::new(start),
+            interactive: io::stderr().is_terminal(),
+        }
+    }
+
+    // Records `samples` finished camera samples and `rays` traced rays,
+    // printing a status line if enough time has passed since the last one
+    pub fn advance(&self, samples: u64, rays: u64, pass: u32, passes: u32) {
+        self.done.fetch_add(samples, Ordering::Relaxed);
+        self.rays.fetch_add(rays, Ordering::Relaxed);
+
+        // Terminals are redrawn in place; logs get a line now and then
+        let interval = if self.interactive {
+            Duration::from_millis(200)
+        } else {
+            Duration::from_secs(5)
+        };
+        let Ok(mut last_report) = self.last_report.try_lock() else {
+            return;
+        };
+        if last_report.elapsed() >= interval {
+            *last_report = Instant::now();
+            self.print(pass, passes);
+        }
+    }
+
+    // Prints the final status line
+    pub fn finish(&self, pass: u32, passes: u32) {
+        let _guard = self.last_report.lock();
+        self.print(pass, passes);
+        if self.interactive {
+            eprintln!();
+        }
+    }
+
+    fn print(&self, pass: u32, passes: u32) {
+        let done = self.done.load(Ordering::Relaxed).min(self.total);
+        let fraction = done as f64 / self.total as f64;
+        let elapsed = self.start.elapsed().as_secs_f64();
+        let eta = if done > 0 {
+            duration(elapsed * (1.0 - fraction) / fraction)
+        } else {
+            "--:--".to_string()
+        };
+        let rays_per_second = self.rays.load(Ordering::Relaxed) as f64 / elapsed.max(1e-9);
+
+        let mut line = format!(
+            "{:5.1}% | {} elapsed | ETA {} | {} rays/s",
+            100.0 * fraction,
+            duration(elapsed),
+            eta,
+            count(rays_per_second)
+        );
+        if passes > 1 {
+            line = format!("pass {}/{} | {}", pass + 1, passes, line);
+        }
+
+        let mut stderr = io::stderr().lock();
+        let _ = if self.interactive {
+            // Trailing spaces wipe leftovers from a longer previous line
+            write!(stderr, "\r{}   ", line)
+        } else {
+            writeln!(stderr, "{}", line)
+        };
+        let _ = stderr.flush();
+    }
+}
+
+// `m:ss`, or `h:mm:ss` for an hour or more
+fn duration(seconds: f64) -> String {
+    let seconds = seconds.round() as u64;
+    let (hours, minutes, seconds) = (seconds / 3600, seconds / 60 % 60, seconds % 60);
+    if hours > 0 {
+        format!("{}:{:02}:{:02}", hours, minutes, seconds)
+    } else {
+        format!("{}:{:02}", minutes, seconds)
+    }
+}
+
+// Short human-readable count such as `12.3M`
+fn count(value: f64) -> String {
+    if value >= 1e9 {
+        format!("{:.2}G", value / 1e9)
+    } else if value >= 1e6 {
+        format!("{:.2}M", value / 1e6)
+    } else if value >= 1e3 {
+        format!("{:.1}k", value / 1e3)
+    } else {
+        format!("{:.0}", value)
+    }
+}
@@ -0,0 +1,435 @@
+// Tiled, progressive rendering
+//
+// The image is split into square tiles that rayon hands out to worker
+// threads. A pixel's samples are spread over one or more passes; after
+// each pass the image so far can be saved, so long renders show results
+// early. Cancelling stops at the next tile and keeps every finished one.
+
+use crate::aov::{self, Aov, AovPixel};
+use crate::camera::Camera;
+use crate::color::Color;
+use crate::denoise::{Denoiser, Guides};
+use crate::image::Image;
+use crate::progress::{self, Progress};
+use crate::rng::Rng;
+use crate::scene_file::RenderSettings;
+use crate::Scene;
+use rayon::prelude::*;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+#[derive(Debug, Clone, Copy)]
+struct Tile {
+    x: usize,
+    y: usize,
+    width: usize,
+    height: usize,
+}
+
+// Tiles covering the image row by row from the top; tiles along the right
+// and bottom edges are cut to fit
+fn tiles(width: usize, height: usize, size: usize) -> Vec<Tile> {
+    let mut tiles = Vec::new();
+    for y in (0..height).step_by(size) {
+        for x in (0..width).step_by(size) {
+            tiles.push(Tile {
+                x,
+                y,
+                width: size.min(width - x),
+                height: size.min(height - y),
+            });
+        }
+    }
+    tiles
+}
+
+// Below this fraction of the weight of its positive samples, a pixel's
+// filter weights have mostly cancelled out
+const MIN_WEIGHT_FRACTION: f64 = 0.5;
+
+// Filter-weighted sum of the samples in one pixel, next to the same sum
+// with the filter's negative lobes cut off
+#[derive(Debug, Clone, Copy, Default)]
+struct PixelSum {
+    color: Color,
+    weight: f64,
+    positive_color: Color,
+    positive_weight: f64,
+}
+
+impl PixelSum {
+    fn add(&mut self, color: Color, weight: f64) {
+        self.color += color * weight;
+        self.weight += weight;
+        if weight > 0.0 {
+            self.positive_color += color * weight;
+            self.positive_weight += weight;
+        }
+    }
+
+    fn merge(&mut self, other: &PixelSum) {
+        self.color += other.color;
+        self.weight += other.weight;
+        self.positive_color += other.positive_color;
+        self.positive_weight += other.positive_weight;
+    }
+
+    // Weighted average; black if there are no samples. With few samples
+    // the negative lobes can cancel out nearly all of the weight, and
+    // dividing by what is left blows up the noise, so those pixels drop
+    // their negative samples instead.
+    fn average(&self) -> Color {
+        if self.weight > MIN_WEIGHT_FRACTION * self.positive_weight {
+            self.color / self.weight
+        } else if self.positive_weight > 0.0 {
+            self.positive_color / self.positive_weight
+        } else {
+            Color::black()
+        }
+    }
+}
+
+// Filter-weighted sums of the samples taken so far in each pixel
+pub struct Framebuffer {
+    pub width: usize,
+    pub height: usize,
+    sums: Vec<PixelSum>,
+    // Empty unless AOVs were asked for
+    aovs: Vec<AovPixel>,
+}
+
+impl Framebuffer {
+    fn new(width: usize, height: usize, aovs: bool) -> Self {
+        Framebuffer {
+            width,
+            height,
+            sums: vec![PixelSum::default(); width * height],
+            aovs: if aovs { vec![AovPixel::default(); width * height] } else { Vec::new() },
+        }
+    }
+
+    // Average color of pixel (x, y), with (0, 0) at the top left; black
+    // if it has no samples
+    pub fn get(&self, x: usize, y: usize) -> Color {
+        self.sums[y * self.width + x].average()
+    }
+
+    // Average color of every pixel, in linear RGB
+    pub fn image(&self) -> Image {
+        let pixels = (0..self.height)
+            .flat_map(|y| (0..self.width).map(move |x| (x, y)))
+            .map(|(x, y)| self.get(x, y))
+            .collect();
+        Image {
+            width: self.width,
+            height: self.height,
+            pixels,
+        }
+    }
+
+    // Image of one AOV, in its raw values, or `None` if the render did not
+    // trace AOVs
+    pub fn aov(&self, aov: Aov) -> Option<Image> {
+        if self.aovs.is_empty() {
+            return None;
+        }
+        let weights: Vec<f64> = self.sums.iter().map(|sum| sum.weight).collect();
+        Some(aov::resolve(aov, self.width, self.height, &self.aovs, &weights))
+    }
+
+    // The image with its noise filtered out, guided by the normal, albedo
+    // and depth AOVs, or `None` if the render did not trace them
+    pub fn denoised(&self, denoiser: &Denoiser) -> Option<Image> {
+        let guides = Guides {
+            normal: self.aov(Aov::Normal)?,
+            albedo: self.aov(Aov::Albedo)?,
+            depth: self.aov(Aov::Depth)?,
+        };
+        Some(denoiser.apply(&self.image(), &guides))
+    }
+}
+
+// Whether the render needs AOVs, either to save them or to guide the
+// denoiser
+fn traces_aovs(settings: &RenderSettings) -> bool {
+    !settings.aovs.is_empty() || settings.denoise.is_some()
+}
+
+// Renders the whole image without printing anything
+pub fn render(scene: &Scene, camera: &Camera, settings: &RenderSettings) -> Framebuffer {
+    let (framebuffer, _) = run(scene, camera, settings, &AtomicBool::new(false), |_| {}, None);
+    framebuffer
+}
+
+// Renders the scene, printing progress to stderr and calling `on_pass`
+// with the image after every pass but the last. Returns the framebuffer
+// and whether every pass finished before `cancel` was set.
+pub fn render_progressive(
+    scene: &Scene,
+    camera: &Camera,
+    settings: &RenderSettings,
+    cancel: &AtomicBool,
+    on_pass: impl FnMut(&Image),
+) -> (Framebuffer, bool) {
+    let progress = Progress::new((settings.width * settings.height) as u64 * settings.samples as u64);
+    run(scene, camera, settings, cancel, on_pass, Some(&progress))
+}
+
+fn run(
+    scene: &Scene,
+    camera: &Camera,
+    settings: &RenderSettings,
+    cancel: &AtomicBool,
+    mut on_pass: impl FnMut(&Image),
+    progress: Option<&Progress>,
+) -> (Framebuffer, bool) {
+    let (width, height) = (settings.width, settings.height);
+    let tiles = tiles(width, height, settings.tile_size);
+    let mut framebuffer = Framebuffer::new(width, height, traces_aovs(settings));
+
+    // Every pass takes at least one sample per pixel
+    let passes = settings.passes.min(settings.samples);
+    for pass in 0..passes {
+        // Spread the samples as evenly as possible over the passes
+        let samples = pass_samples(settings.samples, passes, pass);
+        let results: Vec<_> = tiles
+            .par_iter()
+            .map(|&tile| {
+                if cancel.load(Ordering::Relaxed) {
+                    return None;
+                }
+                let mut pixels = Vec::with_capacity(tile.width * tile.height);
+                for j in tile.y..tile.y + tile.height {
+                    for i in tile.x..tile.x + tile.width {
+                        pixels.push(render_pixel(scene, camera, settings, i, j, pass, samples));
+                    }
+                }
+                let rays = progress::take_ray_count();
+                if let Some(progress) = progress {
+                    let tile_samples = (tile.width * tile.height) as u64 * samples as u64;
+                    progress.advance(tile_samples, rays, pass, passes);
+                }
+                Some((tile, pixels))
+            })
+            .collect();
+
+        for (tile, pixels) in results.into_iter().flatten() {
+            let mut pixels = pixels.into_iter();
+            for j in tile.y..tile.y + tile.height {
+                for i in tile.x..tile.x + tile.width {
+                    let (sum, aovs) = pixels.next().unwrap();
+                    let index = j * width + i;
+                    framebuffer.sums[index].merge(&sum);
+                    if let Some(aovs) = aovs {
+                        framebuffer.aovs[index].merge(&aovs);
+                    }
+                }
+            }
+        }
+
+        if cancel.load(Ordering::Relaxed) {
+            if let Some(progress) = progress {
+                progress.finish(pass, passes);
+            }
+            return (framebuffer, false);
+        }
+        if pass + 1 < passes {
+            on_pass(&framebuffer.image());
+        }
+    }
+
+    if let Some(progress) = progress {
+        progress.finish(passes - 1, passes);
+    }
+    (framebuffer, true)
+}
+
+// Samples taken in `pass` so that the passes add up to `samples`
+fn pass_samples(samples: u32, passes: u32, pass: u32) -> u32 {
+    let (samples, passes, pass) = (samples as u64, passes as u64, pass as u64);
+    (samples * (pass + 1) / passes - samples * pass / passes) as u32
+}
+
+// Filter-weighted sum of `samples` rays around the center of pixel (i, j)
+// and, if any were asked for, the sums of their AOVs
+fn render_pixel(
+    scene: &Scene,
+    camera: &Camera,
+    settings: &RenderSettings,
+    i: usize,
+    j: usize,
+    pass: u32,
+    samples: u32,
+) -> (PixelSum, Option<AovPixel>) {
+    // Seed per pixel and pass so the image does not depend on thread
+    // scheduling
+    let pixel = (j * settings.width + i) as u64;
+    let stream = pass as u64 * (settings.width * settings.height) as u64 + pixel;
+    let mut rng = Rng::with_stream(settings.seed, stream);
+    let offsets = settings.sampler.offsets(samples, settings.filter.radius(), &mut rng);
+    // AOVs draw from their own stream, keeping the beauty image the same
+    // with or without them
+    let mut aov_rng = Rng::with_stream(!settings.seed, stream);
+    let mut aovs = traces_aovs(settings).then(AovPixel::default);
+    let shadow = settings.aovs.contains(&Aov::Shadow);
+
+    let mut sum = PixelSum::default();
+    for (dx, dy) in offsets {
+        let u = (i as f64 + dx) / (settings.width - 1) as f64;
+        let v = 1.0 - ((j as f64 + dy) / (settings.height - 1) as f64);
+        let weight = settings.filter.evaluate(dx, dy);
+        // Samples outside a fisheye's image circle stay black
+        let mut color = Color::black();
+        if let Some(ray) = camera.get_ray(u, v, &mut rng) {
+            color = settings.integrator.radiance(scene, &ray, settings.max_depth, &mut rng);
+            if let Some(aovs) = &mut aovs {
+                aovs.add(&aov::sample(scene, &ray, shadow, &mut aov_rng), weight);
+            }
+        }
+        sum.add(color, weight);
+    }
+    (sum, aovs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::aabb::Aabb;
+    use crate::camera::Projection;
+    use crate::environment::Background;
+    use crate::hittable::{Hit, Hittable};
+    use crate::vec3::{Point3, Vec3};
+    use crate::Ray;
+    use std::sync::Arc;
+
+    // Invisible object that raises a flag as soon as any ray is traced
+    struct Tripwire(Arc<AtomicBool>);
+
+    impl Hittable for Tripwire {
+        fn intersect(&self, _: &Ray, _: f64, _: f64) -> Option<Hit<'_>> {
+            self.0.store(true, Ordering::Relaxed);
+            None
+        }
+
+        fn bounding_box(&self) -> Option<Aabb> {
+            None
+        }
+    }
+
+    // A gray background seen through 4x4 tiles, with a tripwire on `flag`
+    fn setup(flag: &Arc<AtomicBool>, passes: u32) -> (Scene, Camera, RenderSettings) {
+        let scene = Scene::new(
+            vec![Box::new(Tripwire(flag.clone()))],
+            Vec::new(),
+            0.0,
+            Background::Gradient {
+                bottom: Color::new(0.2, 0.2, 0.2),
+                top: Color::white(),
+            },
+        );
+        let camera = Camera::new(
+            Point3::origin(),
+            Vec3::new(0.0, 0.0, -1.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            Projection::Perspective { fov: 60.0 },
+            1.0,
+        );
+        let settings = RenderSettings {
+            width: 16,
+            height: 16,
+            samples: 4,
+            passes,
+            tile_size: 4,
+            ..RenderSettings::default()
+        };
+        (scene, camera, settings)
+    }
+
+    fn assert_close(a: Color, b: Color) {
+        assert!((a.r - b.r).abs() + (a.g - b.g).abs() + (a.b - b.b).abs() < 1e-12, "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn pixels_average_their_weighted_samples() {
+        let mut sum = PixelSum::default();
+        assert_eq!(sum.average(), Color::black());
+        sum.add(Color::new(1.0, 0.0, 0.0), 3.0);
+        sum.add(Color::new(0.0, 1.0, 0.0), 1.0);
+        assert_close(sum.average(), Color::new(0.75, 0.25, 0.0));
+        // A small negative weight still counts
+        sum.add(Color::new(0.0, 0.0, 1.0), -0.5);
+        assert_close(sum.average(), Color::new(3.0, 1.0, -0.5) / 3.5);
+    }
+
+    #[test]
+    fn cancelled_weights_drop_the_negative_lobes() {
+        // Net weight of 0.01 would scale the difference up a hundredfold
+        let mut sum = PixelSum::default();
+        sum.add(Color::new(0.5, 0.5, 0.5), 0.2);
+        sum.add(Color::new(0.4, 0.4, 0.4), -0.19);
+        assert_close(sum.average(), Color::new(0.5, 0.5, 0.5));
+
+        // Only negative samples: nothing to keep
+        let mut sum = PixelSum::default();
+        sum.add(Color::white(), -0.1);
+        assert_eq!(sum.average(), Color::black());
+
+        // Merging keeps both sums
+        let mut merged = PixelSum::default();
+        merged.merge(&sum);
+        merged.add(Color::new(0.2, 0.2, 0.2), 1.0);
+        assert_close(merged.average(), Color::new(0.1, 0.1, 0.1) / 0.9);
+    }
+
+    #[test]
+    fn cancelling_mid_pass_keeps_the_finished_tiles() {
+        let (scene, camera, settings) = setup(&Arc::new(AtomicBool::new(false)), 1);
+        let full = render(&scene, &camera, &settings).image();
+
+        // The first ray cancels; tiles already started still finish
+        let cancel = Arc::new(AtomicBool::new(false));
+        let (scene, camera, settings) = setup(&cancel, 1);
+        let pool = rayon::ThreadPoolBuilder::new().num_threads(2).build().unwrap();
+        let (framebuffer, finished) = pool.install(|| run(&scene, &camera, &settings, &cancel, |_| {}, None));
+        assert!(!finished);
+
+        let (mut kept, mut skipped) = (0, 0);
+        for tile in tiles(16, 16, 4) {
+            let pixels: Vec<(usize, usize)> = (tile.y..tile.y + tile.height)
+                .flat_map(|y| (tile.x..tile.x + tile.width).map(move |x| (x, y)))
+                .collect();
+            if pixels.iter().all(|&(x, y)| framebuffer.get(x, y) == full.get(x, y)) {
+                kept += 1;
+            } else {
+                assert!(pixels.iter().all(|&(x, y)| framebuffer.get(x, y) == Color::black()));
+                skipped += 1;
+            }
+        }
+        assert!(kept >= 1 && skipped >= 1, "{} kept, {} skipped", kept, skipped);
+    }
+
+    #[test]
+    fn cancelling_between_passes_keeps_the_finished_passes() {
+        // The first pass of two takes 2 of the 4 samples, with the same
+        // random numbers as a single pass of 2
+        let (scene, camera, settings) = setup(&Arc::new(AtomicBool::new(false)), 1);
+        let first_pass = render(&scene, &camera, &RenderSettings { samples: 2, ..settings }).image();
+
+        let cancel = AtomicBool::new(false);
+        let (scene, camera, settings) = setup(&Arc::new(AtomicBool::new(false)), 2);
+        let mut saved = Vec::new();
+        let (framebuffer, finished) = run(
+            &scene,
+            &camera,
+            &settings,
+            &cancel,
+            |image| {
+                saved.push(image.pixels.clone());
+                cancel.store(true, Ordering::Relaxed);
+            },
+            None,
+        );
+        assert!(!finished);
+        assert_eq!(saved, vec![first_pass.pixels.clone()]);
+        assert_eq!(framebuffer.image().pixels, first_pass.pixels);
+    }
+}
@@ -0,0 +1,57 @@
+// Small deterministic pseudo-random number generator (xorshift64*)
+//
+// Renders must be reproducible, so every source of randomness is seeded
+// explicitly rather than drawn from the OS.
+
+use crate::vec3::Vec3;
+
+#[derive(Debug, Clone)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        // Scramble the seed (SplitMix64) so nearby seeds give unrelated streams
+        let mut z = seed.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^= z >> 31;
+        Rng {
+            state: if z == 0 { 1 } else { z },
+        }
+    }
+
+    // Independent stream `stream` of the sequence chosen by `seed`, e.g.
+    // one stream per pixel
+    pub fn with_stream(seed: u64, stream: u64) -> Self {
+        Rng::new(seed.wrapping_mul(0xD129_1B6A_5D79_3C47).wrapping_add(stream))
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        self.state.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    // Uniform in [0, 1)
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 * (1.0 / (1u64 << 53) as f64)
+    }
+
+    // Uniform in [min, max)
+    pub fn range(&mut self, min: f64, max: f64) -> f64 {
+        min + (max - min) * self.next_f64()
+    }
+
+    // Uniform point inside the unit sphere, by rejection sampling
+    pub fn in_unit_sphere(&mut self) -> Vec3 {
+        loop {
+            let p = Vec3::new(self.range(-1.0, 1.0), self.range(-1.0, 1.0), self.range(-1.0, 1.0));
+            if p.dot(&p) < 1.0 {
+                return p;
+            }
+        }
+    }
+}
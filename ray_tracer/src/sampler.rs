@@ -0,0 +1,81 @@
+// Sample positions within a pixel
+
+use crate::rng::Rng;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Sampler {
+    // Every sample at the pixel center (no anti-aliasing)
+    Center,
+    // Independent uniformly random positions
+    Random,
+    // Jittered grid: the footprint is split into equal cells with one
+    // random sample in each, which spreads samples more evenly
+    Stratified,
+}
+
+impl Sampler {
+    // Returns `count` offsets from the pixel center, each within
+    // `radius` pixels along both axes
+    pub fn offsets(&self, count: u32, radius: f64, rng: &mut Rng) -> Vec<(f64, f64)> {
+        let mut offsets = Vec::with_capacity(count as usize);
+        let random = |rng: &mut Rng| (rng.range(-radius, radius), rng.range(-radius, radius));
+
+        match self {
+            Sampler::Center => offsets.resize(count as usize, (0.0, 0.0)),
+            Sampler::Random => offsets.extend((0..count).map(|_| random(rng))),
+            Sampler::Stratified => {
+                // The largest square grid that fits; any remaining samples
+                // are placed randomly
+                let cells = (count as f64).sqrt() as u32;
+                let cell_size = 2.0 * radius / cells as f64;
+                for y in 0..cells {
+                    for x in 0..cells {
+                        offsets.push((
+                            -radius + (x as f64 + rng.next_f64()) * cell_size,
+                            -radius + (y as f64 + rng.next_f64()) * cell_size,
+                        ));
+                    }
+                }
+                offsets.extend((cells * cells..count).map(|_| random(rng)));
+            }
+        }
+        offsets
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn offsets_stay_within_the_radius() {
+        for sampler in [Sampler::Center, Sampler::Random, Sampler::Stratified] {
+            for count in [1, 7, 16] {
+                let offsets = sampler.offsets(count, 1.5, &mut Rng::new(1));
+                assert_eq!(offsets.len(), count as usize);
+                assert!(offsets.iter().all(|&(x, y)| x.abs() <= 1.5 && y.abs() <= 1.5));
+            }
+        }
+        assert_eq!(Sampler::Center.offsets(3, 0.5, &mut Rng::new(1)), vec![(0.0, 0.0); 3]);
+    }
+
+    #[test]
+    fn stratified_offsets_fill_every_cell() {
+        // 16 samples over a 4 x 4 grid of half-pixel cells
+        let offsets = Sampler::Stratified.offsets(16, 1.0, &mut Rng::new(2));
+        let mut cells: Vec<(i64, i64)> = offsets
+            .iter()
+            .map(|&(x, y)| (((x + 1.0) * 2.0).floor() as i64, ((y + 1.0) * 2.0).floor() as i64))
+            .collect();
+        cells.sort();
+        let expected: Vec<(i64, i64)> = (0..4).flat_map(|x| (0..4).map(move |y| (x, y))).collect();
+        assert_eq!(cells, expected);
+    }
+
+    #[test]
+    fn same_rng_gives_the_same_offsets() {
+        let first = Sampler::Random.offsets(8, 0.5, &mut Rng::new(3));
+        assert_eq!(first, Sampler::Random.offsets(8, 0.5, &mut Rng::new(3)));
+        assert_ne!(first, Sampler::Random.offsets(8, 0.5, &mut Rng::new(4)));
+    }
+}
@@ -0,0 +1,25 @@
+// Sampling helpers for Monte Carlo integration
+
+use crate::rng::Rng;
+use crate::vec3::Vec3;
+
+// Two unit vectors that form an orthonormal basis with `n`
+pub fn orthonormal_basis(n: Vec3) -> (Vec3, Vec3) {
+    // Branchless construction (Duff et al. 2017)
+    let sign = 1.0_f64.copysign(n.z);
+    let a = -1.0 / (sign + n.z);
+    let b = n.x * n.y * a;
+    let tangent = Vec3::new(1.0 + sign * n.x * n.x * a, sign * b, -sign * n.x);
+    let bitangent = Vec3::new(b, sign + n.y * n.y * a, -n.y);
+    (tangent, bitangent)
+}
+
+// Direction in the hemisphere around `normal`, with probability
+// proportional to the cosine of its angle to the normal
+pub fn cosine_hemisphere(normal: Vec3, rng: &mut Rng) -> Vec3 {
+    let r = rng.next_f64().sqrt();
+    let phi = 2.0 * std::f64::consts::PI * rng.next_f64();
+    let (tangent, bitangent) = orthonormal_basis(normal);
+    let z = (1.0 - r * r).max(0.0).sqrt();
+    tangent * (r * phi.cos()) + bitangent * (r * phi.sin()) + normal * z
+}
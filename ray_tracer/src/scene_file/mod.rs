@@ -0,0 +1,1929 @@
+// Scene description files
+//
+// Scenes are loaded from a small text format instead of being compiled into
+// the binary. See `scenes/default.scene` for an annotated example.
+
+mod parser;
+
+use crate::animation::{Curve, Key, Track};
+use crate::aov::Aov;
+use crate::camera::{Camera, Projection};
+use crate::color::Color;
+use crate::denoise::Denoiser;
+use crate::environment::{Background, EnvironmentMap};
+use crate::filter::Filter;
+use crate::hittable::Hittable;
+use crate::image::Image;
+use crate::image_io::{self, ColorSpace, ImageFormat};
+use crate::integrator::Integrator;
+use crate::light::{Falloff, Light, LightKind};
+use crate::material::Material;
+use crate::medium::{Fog, Medium, Phase};
+use crate::motion::{Motion, Pose};
+use crate::normal_map::NormalMap;
+use crate::obj;
+use crate::sampler::Sampler;
+use crate::shapes::{AaBox, Csg, CsgOperation, Cylinder, Disk, Instance, Mesh, Plane, Sphere, Triangle};
+use crate::texture::{Perlin, Texture, UvAxis, Wrap};
+use crate::tone_map::{Gamma, OutputTransform, ToneMap};
+use crate::transform::{Matrix4, Transform};
+use crate::vec3::{Point3, Vec3};
+use crate::Scene;
+use parser::{Arg, Node, Position, Value};
+use std::{collections::HashMap, fmt, fs, io, path::Path, path::PathBuf, sync::Arc};
+
+#[derive(Debug)]
+pub enum SceneError {
+    Io(io::Error),
+    Syntax {
+        line: usize,
+        column: usize,
+        message: String,
+    },
+}
+
+impl SceneError {
+    fn at(pos: Position, message: impl Into<String>) -> Self {
+        SceneError::Syntax {
+            line: pos.line,
+            column: pos.column,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SceneError::Io(err) => write!(f, "{}", err),
+            SceneError::Syntax {
+                line,
+                column,
+                message,
+            } => write!(f, "line {}, column {}: {}", line, column, message),
+        }
+    }
+}
+
+impl std::error::Error for SceneError {}
+
+impl From<io::Error> for SceneError {
+    fn from(err: io::Error) -> Self {
+        SceneError::Io(err)
+    }
+}
+
+// Output options that live in the `settings` block
+pub struct RenderSettings {
+    pub width: usize,
+    pub height: usize,
+    pub output: PathBuf,
+    // `None` picks the format from the output file's extension
+    pub format: Option<ImageFormat>,
+    // How many bounces a ray may take
+    pub max_depth: u32,
+    pub integrator: Integrator,
+    // Rays traced per pixel, placed by `sampler` and weighted by `filter`
+    pub samples: u32,
+    pub sampler: Sampler,
+    pub filter: Filter,
+    // Changing the seed changes the noise pattern; the same seed always
+    // reproduces the same image
+    pub seed: u64,
+    // Side length of the square tiles handed to worker threads, in pixels
+    pub tile_size: usize,
+    // Number of passes the samples are spread over; the image is saved
+    // after each one
+    pub passes: u32,
+    // Exposure, tone mapping and gamma for integer image formats
+    pub output_transform: OutputTransform,
+    // First and last frame of an animation, inclusive; `None` for a still
+    pub frames: Option<(u32, u32)>,
+    // Extra images saved next to the output, see `Aov::output`
+    pub aovs: Vec<Aov>,
+    // Filters the final image if set
+    pub denoise: Option<Denoiser>,
+}
+
+impl Default for RenderSettings {
+    fn default() -> Self {
+        RenderSettings {
+            width: 800,
+            height: 600,
+            output: PathBuf::from("output.ppm"),
+            format: None,
+            max_depth: 5,
+            integrator: Integrator::Direct,
+            samples: 1,
+            sampler: Sampler::Stratified,
+            filter: Filter::default(),
+            seed: 0,
+            tile_size: 32,
+            passes: 1,
+            output_transform: OutputTransform::default(),
+            frames: None,
+            aovs: Vec::new(),
+            denoise: None,
+        }
+    }
+}
+
+impl RenderSettings {
+    // Output file of one animation frame. A run of `#` in the file name is
+    // replaced by the zero-padded frame number, so `frame_####.png` gives
+    // `frame_0001.png`; without one, `_0001` is added before the extension.
+    pub fn frame_output(&self, frame: u32) -> PathBuf {
+        let name = self.output.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+        let name = match name.find('#') {
+            Some(start) => {
+                let width = name[start..].chars().take_while(|&c| c == '#').count();
+                format!("{}{:0width$}{}", &name[..start], frame, &name[start + width..], width = width)
+            }
+            None => {
+                let stem = self.output.file_stem().map(|stem| stem.to_string_lossy().into_owned()).unwrap_or_default();
+                match self.output.extension() {
+                    Some(extension) => format!("{}_{:04}.{}", stem, frame, extension.to_string_lossy()),
+                    None => format!("{}_{:04}", stem, frame),
+                }
+            }
+        };
+        self.output.with_file_name(name)
+    }
+}
+
+// The camera is only built once the final image size (and therefore the
+// aspect ratio) is known, since the command line may override it.
+pub struct CameraSettings {
+    pub position: Point3,
+    pub direction: Vec3,
+    // Point to aim at, overriding `direction`
+    pub look_at: Option<Point3>,
+    pub up: Vec3,
+    pub projection: Projection,
+    // Lens diameter; 0 is a pinhole with everything in focus
+    pub aperture: f64,
+    pub focus_distance: f64,
+    // Open and close times; equal times disable motion blur
+    pub shutter: (f64, f64),
+}
+
+impl Default for CameraSettings {
+    fn default() -> Self {
+        CameraSettings {
+            position: Point3::origin(),
+            direction: Vec3::new(0.0, 0.0, -1.0),
+            look_at: None,
+            up: Vec3::new(0.0, 1.0, 0.0),
+            projection: Projection::Perspective { fov: 90.0 },
+            aperture: 0.0,
+            focus_distance: 1.0,
+            shutter: (0.0, 0.0),
+        }
+    }
+}
+
+impl CameraSettings {
+    pub fn build(&self, aspect_ratio: f64) -> Camera {
+        let camera = match self.look_at {
+            Some(target) => Camera::look_at(self.position, target, self.up, self.projection, aspect_ratio),
+            None => Camera::new(self.position, self.direction, self.up, self.projection, aspect_ratio),
+        };
+        camera
+            .with_lens(self.aperture, self.focus_distance)
+            .with_shutter(self.shutter.0, self.shutter.1)
+    }
+}
+
+pub struct SceneDescription {
+    pub scene: Scene,
+    pub camera: CameraSettings,
+    pub settings: RenderSettings,
+}
+
+// Loads the scene as a still image. Animated scenes are shown at their
+// first frame.
+pub fn load(path: &Path) -> Result<SceneDescription, SceneError> {
+    let source = fs::read_to_string(path)?;
+    parse(&source, path.parent().unwrap_or(Path::new("")))
+}
+
+// Loads the scene as it is at `frame` of an animation
+pub fn load_frame(path: &Path, frame: u32) -> Result<SceneDescription, SceneError> {
+    let source = fs::read_to_string(path)?;
+    parse_frame(&source, path.parent().unwrap_or(Path::new("")), frame)
+}
+
+// Relative paths inside the scene (such as mesh files) are resolved
+// against `base_dir`.
+pub fn parse(source: &str, base_dir: &Path) -> Result<SceneDescription, SceneError> {
+    let nodes = parser::parse(source)?;
+    let frame = first_frame(&nodes)?;
+    build(&nodes, base_dir, frame)
+}
+
+pub fn parse_frame(source: &str, base_dir: &Path, frame: u32) -> Result<SceneDescription, SceneError> {
+    let nodes = parser::parse(source)?;
+    build(&nodes, base_dir, frame)
+}
+
+fn build(nodes: &[Node], base_dir: &Path, frame: u32) -> Result<SceneDescription, SceneError> {
+    Builder::new(base_dir, frame as f64).build(&animate(nodes, frame as f64)?)
+}
+
+// The start of the `frames` setting, or 0 for scenes that are not
+// animations
+fn first_frame(nodes: &[Node]) -> Result<u32, SceneError> {
+    let mut first = 0;
+    for node in nodes.iter().filter(|node| node.name == "settings") {
+        if let Some(child) = node.children.iter().find(|child| child.name == "frames") {
+            first = frames(child.leaf()?)?.0;
+        }
+    }
+    Ok(first)
+}
+
+// Replaces every keyframed property with its value at `frame`. A property
+// is keyframed when it has a block of `key <frame> <values...> [curve]`
+// lines instead of values:
+//
+//     position {
+//         key 1 0 1 5
+//         key 48 0 3 5
+//     }
+fn animate(nodes: &[Node], frame: f64) -> Result<Vec<Node>, SceneError> {
+    nodes
+        .iter()
+        .map(|node| {
+            if !is_keyframed(node) {
+                return Ok(Node {
+                    name: node.name.clone(),
+                    args: node.args.clone(),
+                    children: animate(&node.children, frame)?,
+                    pos: node.pos,
+                });
+            }
+            let args = track(node)?
+                .sample(frame)
+                .into_iter()
+                .map(|value| Arg {
+                    value: Value::Number(value),
+                    pos: node.pos,
+                })
+                .collect();
+            Ok(Node {
+                name: node.name.clone(),
+                args,
+                children: Vec::new(),
+                pos: node.pos,
+            })
+        })
+        .collect()
+}
+
+// `motion` blocks also contain `key` entries, but with a block of their own
+fn is_keyframed(node: &Node) -> bool {
+    node.args.is_empty()
+        && !node.children.is_empty()
+        && node.name != "motion"
+        && node.children.iter().all(|child| child.name == "key" && child.children.is_empty())
+}
+
+fn track(node: &Node) -> Result<Track, SceneError> {
+    let mut keys: Vec<Key> = Vec::new();
+    for child in &node.children {
+        let Some((first, rest)) = child.args.split_first() else {
+            return Err(child.error("`key` expects a frame followed by values"));
+        };
+        let frame = first.number()?;
+        // An optional curve name ends the line
+        let (curve, values) = match rest.split_last() {
+            Some((last, values)) if matches!(last.value, Value::Text(_)) => {
+                let name = last.text()?;
+                let curve = Curve::from_name(name).ok_or_else(|| {
+                    SceneError::at(
+                        last.pos,
+                        format!(
+                            "unknown curve `{}` (expected step, linear, ease_in, ease_out, ease or smooth)",
+                            name
+                        ),
+                    )
+                })?;
+                (curve, values)
+            }
+            _ => (Curve::Linear, rest),
+        };
+        let values = values.iter().map(|arg| arg.number()).collect::<Result<Vec<_>, _>>()?;
+        if values.is_empty() {
+            return Err(child.error("`key` expects a frame followed by values"));
+        }
+        if let Some(previous) = keys.first() {
+            if previous.values.len() != values.len() {
+                return Err(child.error(format!(
+                    "every key of `{}` needs {} values, found {}",
+                    node.name,
+                    previous.values.len(),
+                    values.len()
+                )));
+            }
+        }
+        keys.push(Key { frame, values, curve });
+    }
+    Ok(Track::new(keys))
+}
+
+struct Builder {
+    base_dir: PathBuf,
+    // Frame being built; shifts the camera shutter so that `motion` key
+    // times count in frames
+    frame: f64,
+    materials: HashMap<String, Arc<Material>>,
+    // Every `material` block in order, including ones whose name was
+    // reused, for the material ID AOV
+    declared: Vec<Arc<Material>>,
+    textures: HashMap<String, Texture>,
+    // The file of each image texture and its `color_space`, if given, so
+    // that one used as a normal or bump map can be loaded linear instead
+    image_files: HashMap<String, (Node, Option<ColorSpace>)>,
+    // Objects declared with `object`, shared by every `instance` of them
+    definitions: HashMap<String, Arc<dyn Hittable>>,
+}
+
+impl Builder {
+    fn new(base_dir: &Path, frame: f64) -> Self {
+        Builder {
+            base_dir: base_dir.to_path_buf(),
+            frame,
+            materials: HashMap::new(),
+            declared: Vec::new(),
+            textures: HashMap::new(),
+            image_files: HashMap::new(),
+            definitions: HashMap::new(),
+        }
+    }
+
+    fn build(mut self, nodes: &[Node]) -> Result<SceneDescription, SceneError> {
+        let mut settings = RenderSettings::default();
+        let mut camera = CameraSettings::default();
+        let mut objects: Vec<Box<dyn Hittable>> = Vec::new();
+        let mut lights = Vec::new();
+        let mut ambient_intensity = 0.1;
+        let mut background = Background::Solid(Color::black());
+        let mut environment_light = None;
+        let mut media = Vec::new();
+        let mut fog = None;
+
+        for node in nodes {
+            match node.name.as_str() {
+                "settings" => self.settings(node, &mut settings)?,
+                "camera" => self.camera(node, &mut camera)?,
+                "material" => self.material(node)?,
+                "texture" => self.texture(node)?,
+                name if SHAPES.contains(&name) => objects.push(self.shape(node)?),
+                "object" => self.object(node)?,
+                "light" => lights.push(self.light(node)?),
+                "ambient" => ambient_intensity = node.leaf()?.number()?,
+                "background" => (background, environment_light) = self.background(node)?,
+                "medium" => media.push(self.medium(node)?),
+                "fog" => fog = Some(self::fog(node)?),
+                _ => return Err(unknown(node, None)),
+            }
+        }
+
+        camera.shutter = (camera.shutter.0 + self.frame, camera.shutter.1 + self.frame);
+        lights.extend(environment_light);
+        let mut scene = Scene::new(objects, lights, ambient_intensity, background)
+            .with_media(media)
+            .with_materials(self.declared);
+        if let Some(fog) = fog {
+            scene = scene.with_fog(fog);
+        }
+        Ok(SceneDescription {
+            scene,
+            camera,
+            settings,
+        })
+    }
+
+    fn settings(&self, node: &Node, settings: &mut RenderSettings) -> Result<(), SceneError> {
+        for child in &node.children {
+            let child = child.leaf()?;
+            match child.name.as_str() {
+                "width" => settings.width = dimension(child)?,
+                "height" => settings.height = dimension(child)?,
+                "output" => settings.output = PathBuf::from(child.text()?),
+                "format" => {
+                    let name = child.text()?;
+                    settings.format = Some(ImageFormat::from_name(name).ok_or_else(|| {
+                        SceneError::at(
+                            child.args[0].pos,
+                            format!(
+                                "unknown format `{}` (expected ppm, ppm-binary, png, png16 or exr)",
+                                name
+                            ),
+                        )
+                    })?);
+                }
+                "max_depth" => settings.max_depth = count(child)?,
+                "integrator" => {
+                    settings.integrator = match child.text()? {
+                        "direct" => Integrator::Direct,
+                        "path" => Integrator::Path,
+                        other => {
+                            return Err(SceneError::at(
+                                child.args[0].pos,
+                                format!("unknown integrator `{}` (expected direct or path)", other),
+                            ))
+                        }
+                    }
+                }
+                "samples" => {
+                    settings.samples = count(child)?;
+                    if settings.samples == 0 {
+                        return Err(SceneError::at(child.args[0].pos, "`samples` must be at least 1"));
+                    }
+                }
+                "sampler" => {
+                    settings.sampler = match child.text()? {
+                        "center" => Sampler::Center,
+                        "random" => Sampler::Random,
+                        "stratified" => Sampler::Stratified,
+                        other => {
+                            return Err(SceneError::at(
+                                child.args[0].pos,
+                                format!("unknown sampler `{}` (expected center, random or stratified)", other),
+                            ))
+                        }
+                    }
+                }
+                "filter" => settings.filter = filter(child)?,
+                "seed" => settings.seed = count(child)? as u64,
+                "tile_size" => {
+                    settings.tile_size = count(child)? as usize;
+                    if settings.tile_size == 0 {
+                        return Err(SceneError::at(child.args[0].pos, "`tile_size` must be at least 1"));
+                    }
+                }
+                "passes" => {
+                    settings.passes = count(child)?;
+                    if settings.passes == 0 {
+                        return Err(SceneError::at(child.args[0].pos, "`passes` must be at least 1"));
+                    }
+                }
+                "exposure" => settings.output_transform.exposure = child.number()?,
+                "tone_map" => {
+                    let name = child.text()?;
+                    settings.output_transform.tone_map = ToneMap::from_name(name).ok_or_else(|| {
+                        SceneError::at(
+                            child.args[0].pos,
+                            format!("unknown tone map `{}` (expected clamp, reinhard or aces)", name),
+                        )
+                    })?;
+                }
+                "gamma" => settings.output_transform.gamma = gamma(child)?,
+                "frames" => settings.frames = Some(frames(child)?),
+                "aovs" => settings.aovs = aovs(child)?,
+                // `denoise` or `denoise <strength>`
+                "denoise" => {
+                    let strength = match child.args.len() {
+                        0 => Denoiser::default().strength,
+                        _ => positive(child)?,
+                    };
+                    settings.denoise = Some(Denoiser { strength });
+                }
+                _ => return Err(unknown(child, Some(node))),
+            }
+        }
+        Ok(())
+    }
+
+    fn camera(&self, node: &Node, camera: &mut CameraSettings) -> Result<(), SceneError> {
+        let kind = block_type(
+            node,
+            "camera",
+            "perspective",
+            &[
+                (
+                    "perspective",
+                    &["position", "direction", "look_at", "up", "fov", "aperture", "focus_distance", "shutter"],
+                ),
+                ("orthographic", &["position", "direction", "look_at", "up", "height", "shutter"]),
+                ("fisheye", &["position", "direction", "look_at", "up", "fov", "shutter"]),
+                ("equirectangular", &["position", "direction", "look_at", "up", "shutter"]),
+            ],
+        )?;
+
+        let mut fov = if kind == "fisheye" { 180.0 } else { 90.0 };
+        let mut height = 2.0;
+        let mut focus_distance = None;
+        let mut aimed = false;
+        let mut up = None;
+        for child in &node.children {
+            let child = child.leaf()?;
+            match child.name.as_str() {
+                "position" => camera.position = point(child)?,
+                "direction" | "look_at" if aimed => {
+                    return Err(child.error("`direction` and `look_at` cannot be combined"));
+                }
+                "direction" => {
+                    camera.direction = self::direction(child)?;
+                    camera.look_at = None;
+                    aimed = true;
+                }
+                "look_at" => {
+                    camera.look_at = Some(point(child)?);
+                    aimed = true;
+                }
+                "up" => {
+                    camera.up = self::direction(child)?;
+                    up = Some(child);
+                }
+                "fov" => {
+                    fov = positive(child)?;
+                    // A pinhole cannot see half of everything around it
+                    if kind == "perspective" && fov >= 180.0 {
+                        return Err(SceneError::at(
+                            child.args[0].pos,
+                            "`fov` must be less than 180 degrees",
+                        ));
+                    }
+                }
+                "height" => height = positive(child)?,
+                "aperture" => camera.aperture = non_negative(child)?,
+                "focus_distance" => focus_distance = Some(positive(child)?),
+                // `shutter <open> <close>`
+                "shutter" => {
+                    let v = child.numbers(2)?;
+                    if v[1] < v[0] {
+                        return Err(child.error("`shutter` must close after it opens"));
+                    }
+                    camera.shutter = (v[0], v[1]);
+                }
+                _ => {}
+            }
+        }
+
+        // The camera's sideways axis is the cross product of the two
+        let forward = match camera.look_at {
+            Some(target) => target - camera.position,
+            None => camera.direction,
+        };
+        if forward.length() == 0.0 {
+            return Err(node.error("`look_at` must differ from `position`"));
+        }
+        if forward.normalize().cross(&camera.up.normalize()).length() < 1e-9 {
+            return Err(up.unwrap_or(node).error("`up` must not be parallel to the view direction"));
+        }
+
+        camera.projection = match kind {
+            "orthographic" => Projection::Orthographic { height },
+            "fisheye" => Projection::Fisheye { fov },
+            "equirectangular" => Projection::Equirectangular,
+            _ => Projection::Perspective { fov },
+        };
+        // Without an explicit distance, focus on the look-at target
+        camera.focus_distance = match (focus_distance, camera.look_at) {
+            (Some(distance), _) => distance,
+            (None, Some(target)) => (target - camera.position).length().max(1e-6),
+            (None, None) if camera.aperture > 0.0 => return Err(missing(node, "focus_distance")),
+            (None, None) => 1.0,
+        };
+        if kind != "perspective" {
+            camera.aperture = 0.0;
+        }
+        Ok(())
+    }
+
+    fn material(&mut self, node: &Node) -> Result<(), SceneError> {
+        let name = node.text()?.to_string();
+        let kind = block_type(
+            node,
+            "material",
+            "lambertian",
+            &[
+                ("lambertian", &["color", "texture", "normal_map", "normal_strength", "bump", "bump_height"]),
+                (
+                    "phong",
+                    &[
+                        "color",
+                        "texture",
+                        "specular",
+                        "shininess",
+                        "ka",
+                        "kd",
+                        "ks",
+                        "normal_map",
+                        "normal_strength",
+                        "bump",
+                        "bump_height",
+                    ],
+                ),
+                (
+                    "metal",
+                    &["color", "texture", "roughness", "normal_map", "normal_strength", "bump", "bump_height"],
+                ),
+                ("dielectric", &["ior", "normal_map", "normal_strength", "bump", "bump_height"]),
+            ],
+        )?;
+
+        let mut albedo = Texture::Solid(Color::white());
+        let mut specular = Color::white();
+        let mut shininess = 32.0;
+        let (mut ka, mut kd, mut ks) = (1.0, 1.0, 0.5);
+        let mut roughness = 0.0;
+        let mut ior = 1.5;
+        let mut normal_map = None;
+        let mut normal_strength = 1.0;
+        let mut bump = None;
+        let mut bump_height = 0.05;
+        for child in &node.children {
+            let child = child.leaf()?;
+            match child.name.as_str() {
+                "color" => albedo = Texture::Solid(color(child)?),
+                "texture" => albedo = self.lookup_texture(child)?,
+                "specular" => specular = color(child)?,
+                "shininess" => shininess = positive(child)?,
+                "ka" => ka = non_negative(child)?,
+                "kd" => kd = non_negative(child)?,
+                "ks" => ks = non_negative(child)?,
+                "roughness" => roughness = non_negative(child)?,
+                "ior" => ior = positive(child)?,
+                "normal_map" => normal_map = Some(self.lookup_map(child)?),
+                "normal_strength" => normal_strength = non_negative(child)?,
+                "bump" => bump = Some(self.lookup_map(child)?),
+                "bump_height" => bump_height = child.number()?,
+                _ => {}
+            }
+        }
+
+        let mut material = match kind {
+            "lambertian" => Material::Lambertian { albedo },
+            "phong" => Material::Phong {
+                albedo,
+                specular,
+                shininess,
+                ka,
+                kd,
+                ks,
+            },
+            "metal" => Material::Metal { albedo, roughness },
+            _ => Material::Dielectric { ior },
+        };
+        if let Some(texture) = bump {
+            material = Material::Mapped {
+                base: Box::new(material),
+                map: NormalMap::Bump {
+                    texture,
+                    height: bump_height,
+                },
+            };
+        }
+        if let Some(texture) = normal_map {
+            material = Material::Mapped {
+                base: Box::new(material),
+                map: NormalMap::Tangent {
+                    texture,
+                    strength: normal_strength,
+                },
+            };
+        }
+        let material = Arc::new(material);
+        self.declared.push(material.clone());
+        self.materials.insert(name, material);
+        Ok(())
+    }
+
+    fn texture(&mut self, node: &Node) -> Result<(), SceneError> {
+        let name = node.text()?.to_string();
+        let kind = block_type(
+            node,
+            "texture",
+            "image",
+            &[
+                ("image", &["file", "wrap", "scale", "color_space"]),
+                ("checker", &["even", "odd", "scale"]),
+                ("gradient", &["from", "to", "axis"]),
+                ("noise", &["color", "scale", "seed"]),
+                ("marble", &["color", "vein", "scale", "turbulence", "seed"]),
+            ],
+        )?;
+
+        let mut file = None;
+        let mut wrap = Wrap::Repeat;
+        let mut color_space = None;
+        let mut scale = 1.0;
+        let mut even = Color::white();
+        let mut odd = Color::black();
+        let mut from = Color::black();
+        let mut to = Color::white();
+        let mut axis = UvAxis::V;
+        let mut color = Color::white();
+        let mut vein = Color::new(0.2, 0.2, 0.25);
+        let mut turbulence = 5.0;
+        let mut seed = 0;
+        for child in &node.children {
+            let child = child.leaf()?;
+            match child.name.as_str() {
+                "file" => file = Some(child),
+                "wrap" => {
+                    wrap = match child.text()? {
+                        "repeat" => Wrap::Repeat,
+                        "clamp" => Wrap::Clamp,
+                        "mirror" => Wrap::Mirror,
+                        other => {
+                            return Err(SceneError::at(
+                                child.args[0].pos,
+                                format!("unknown wrap mode `{}` (expected repeat, clamp or mirror)", other),
+                            ))
+                        }
+                    }
+                }
+                "color_space" => {
+                    let name = child.text()?;
+                    color_space = Some(ColorSpace::from_name(name).ok_or_else(|| {
+                        SceneError::at(
+                            child.args[0].pos,
+                            format!("unknown color space `{}` (expected srgb or linear)", name),
+                        )
+                    })?)
+                }
+                "scale" => scale = positive(child)?,
+                "even" => even = self::color(child)?,
+                "odd" => odd = self::color(child)?,
+                "from" => from = self::color(child)?,
+                "to" => to = self::color(child)?,
+                "axis" => {
+                    axis = match child.text()? {
+                        "u" => UvAxis::U,
+                        "v" => UvAxis::V,
+                        other => {
+                            return Err(SceneError::at(
+                                child.args[0].pos,
+                                format!("unknown axis `{}` (expected u or v)", other),
+                            ))
+                        }
+                    }
+                }
+                "color" => color = self::color(child)?,
+                "vein" => vein = self::color(child)?,
+                "turbulence" => turbulence = child.number()?,
+                "seed" => seed = count(child)? as u64,
+                _ => {}
+            }
+        }
+
+        let texture = match kind {
+            "image" => {
+                let file = file.ok_or_else(|| missing(node, "file"))?;
+                let image = self.image(file, color_space.unwrap_or(ColorSpace::Srgb))?;
+                self.image_files.insert(name.clone(), (file.clone(), color_space));
+                Texture::Image {
+                    image: Arc::new(image),
+                    scale,
+                    wrap,
+                }
+            }
+            "checker" => Texture::Checker { even, odd, scale },
+            "gradient" => Texture::Gradient { from, to, axis },
+            "noise" => Texture::Noise {
+                color,
+                scale,
+                noise: Arc::new(Perlin::new(seed)),
+            },
+            _ => Texture::Marble {
+                color,
+                vein,
+                scale,
+                turbulence,
+                noise: Arc::new(Perlin::new(seed)),
+            },
+        };
+        self.textures.insert(name, texture);
+        Ok(())
+    }
+
+    // Any shape block, placed by its transform properties if it has any
+    fn shape(&self, node: &Node) -> Result<Box<dyn Hittable>, SceneError> {
+        let shape: Box<dyn Hittable> = match node.name.as_str() {
+            "sphere" => Box::new(self.sphere(node)?),
+            "plane" => Box::new(self.plane(node)?),
+            "triangle" => Box::new(self.triangle(node)?),
+            "box" => Box::new(self.aa_box(node)?),
+            "disk" => Box::new(self.disk(node)?),
+            "cylinder" => Box::new(self.cylinder(node)?),
+            "mesh" => Box::new(self.mesh(node)?),
+            "union" | "intersection" | "difference" => Box::new(self.csg(node)?),
+            "instance" => return Ok(Box::new(self.instance(node)?)),
+            _ => return Err(unknown(node, None)),
+        };
+        let transform = transform(node)?;
+        let motion = motion(node)?;
+        if transform.is_identity() && motion.is_none() {
+            return Ok(shape);
+        }
+        let instance = Instance::new(Arc::from(shape), transform);
+        Ok(match motion {
+            Some(motion) => Box::new(instance.with_motion(motion)),
+            None => Box::new(instance),
+        })
+    }
+
+    // `object <name> { <shape> }` declares a shape without adding it to the
+    // scene; `instance <name>` blocks then place copies of it
+    fn object(&mut self, node: &Node) -> Result<(), SceneError> {
+        let name = node.text()?.to_string();
+        let [shape] = node.children.as_slice() else {
+            return Err(node.error("`object` must contain exactly one shape"));
+        };
+        let shape = self.shape(shape)?;
+        self.definitions.insert(name, Arc::from(shape));
+        Ok(())
+    }
+
+    fn instance(&self, node: &Node) -> Result<Instance, SceneError> {
+        let name = node.text()?;
+        let object = self
+            .definitions
+            .get(name)
+            .cloned()
+            .ok_or_else(|| SceneError::at(node.args[0].pos, format!("unknown object `{}`", name)))?;
+        if let Some(child) = properties(node).find(|child| !TRANSFORMS.contains(&child.name.as_str())) {
+            return Err(unknown(child, Some(node)));
+        }
+        let instance = Instance::new(object, transform(node)?);
+        Ok(match motion(node)? {
+            Some(motion) => instance.with_motion(motion),
+            None => instance,
+        })
+    }
+
+    // `union`, `intersection` or `difference` of two or more shapes. A
+    // difference takes every later shape away from the first. Surface
+    // properties on the block replace the materials of all its shapes.
+    fn csg(&self, node: &Node) -> Result<Csg, SceneError> {
+        node.numbers(0)?;
+        let operation = match node.name.as_str() {
+            "union" => CsgOperation::Union,
+            "intersection" => CsgOperation::Intersection,
+            _ => CsgOperation::Difference,
+        };
+        let mut shapes = Vec::new();
+        let mut material = None;
+        for child in properties(node) {
+            match child.name.as_str() {
+                name if SHAPES.contains(&name) => shapes.push(self.shape(child)?),
+                name if TRANSFORMS.contains(&name) => {}
+                _ => {
+                    let mut override_material = default_material();
+                    self.surface(child.leaf()?, node, &mut override_material)?;
+                    material = Some(override_material);
+                }
+            }
+        }
+        if shapes.len() < 2 {
+            return Err(node.error(format!("`{}` needs at least 2 shapes", node.name)));
+        }
+
+        let mut shapes = shapes.into_iter();
+        let first = shapes.next().unwrap();
+        let mut csg = Csg::new(operation, first, shapes.next().unwrap());
+        for shape in shapes {
+            csg = Csg::new(operation, Box::new(csg), shape);
+        }
+        Ok(match material {
+            Some(material) => csg.with_material(material),
+            None => csg,
+        })
+    }
+
+    fn sphere(&self, node: &Node) -> Result<Sphere, SceneError> {
+        node.numbers(0)?;
+        let mut center = None;
+        let mut radius = None;
+        let mut material = default_material();
+        for child in properties(node) {
+            let child = child.leaf()?;
+            match child.name.as_str() {
+                "center" => center = Some(point(child)?),
+                "radius" => radius = Some(positive(child)?),
+                _ => self.surface(child, node, &mut material)?,
+            }
+        }
+        Ok(Sphere::new(
+            center.ok_or_else(|| missing(node, "center"))?,
+            radius.ok_or_else(|| missing(node, "radius"))?,
+            material,
+        ))
+    }
+
+    fn plane(&self, node: &Node) -> Result<Plane, SceneError> {
+        node.numbers(0)?;
+        let mut point = None;
+        let mut normal = None;
+        let mut material = default_material();
+        for child in properties(node) {
+            let child = child.leaf()?;
+            match child.name.as_str() {
+                "point" => point = Some(self::point(child)?),
+                "normal" => normal = Some(direction(child)?),
+                _ => self.surface(child, node, &mut material)?,
+            }
+        }
+        Ok(Plane::new(
+            point.ok_or_else(|| missing(node, "point"))?,
+            normal.ok_or_else(|| missing(node, "normal"))?,
+            material,
+        ))
+    }
+
+    fn triangle(&self, node: &Node) -> Result<Triangle, SceneError> {
+        node.numbers(0)?;
+        let mut vertices = Vec::new();
+        let mut material = default_material();
+        for child in properties(node) {
+            let child = child.leaf()?;
+            match child.name.as_str() {
+                "vertex" if vertices.len() == 3 => {
+                    return Err(child.error("a triangle has exactly 3 vertices"));
+                }
+                "vertex" => vertices.push(point(child)?),
+                _ => self.surface(child, node, &mut material)?,
+            }
+        }
+        if vertices.len() != 3 {
+            return Err(node.error(format!(
+                "`triangle` needs 3 `vertex` entries, found {}",
+                vertices.len()
+            )));
+        }
+        Ok(Triangle::new(vertices[0], vertices[1], vertices[2], material))
+    }
+
+    fn aa_box(&self, node: &Node) -> Result<AaBox, SceneError> {
+        node.numbers(0)?;
+        let mut min = None;
+        let mut max = None;
+        let mut material = default_material();
+        for child in properties(node) {
+            let child = child.leaf()?;
+            match child.name.as_str() {
+                "min" => min = Some(point(child)?),
+                "max" => max = Some(point(child)?),
+                _ => self.surface(child, node, &mut material)?,
+            }
+        }
+        Ok(AaBox::new(
+            min.ok_or_else(|| missing(node, "min"))?,
+            max.ok_or_else(|| missing(node, "max"))?,
+            material,
+        ))
+    }
+
+    fn disk(&self, node: &Node) -> Result<Disk, SceneError> {
+        node.numbers(0)?;
+        let mut center = None;
+        let mut normal = None;
+        let mut radius = None;
+        let mut material = default_material();
+        for child in properties(node) {
+            let child = child.leaf()?;
+            match child.name.as_str() {
+                "center" => center = Some(point(child)?),
+                "normal" => normal = Some(direction(child)?),
+                "radius" => radius = Some(positive(child)?),
+                _ => self.surface(child, node, &mut material)?,
+            }
+        }
+        Ok(Disk::new(
+            center.ok_or_else(|| missing(node, "center"))?,
+            normal.ok_or_else(|| missing(node, "normal"))?,
+            radius.ok_or_else(|| missing(node, "radius"))?,
+            material,
+        ))
+    }
+
+    fn cylinder(&self, node: &Node) -> Result<Cylinder, SceneError> {
+        node.numbers(0)?;
+        let mut base = None;
+        let mut top = None;
+        let mut radius = None;
+        let mut material = default_material();
+        for child in properties(node) {
+            let child = child.leaf()?;
+            match child.name.as_str() {
+                "base" => base = Some(point(child)?),
+                "top" => top = Some(point(child)?),
+                "radius" => radius = Some(positive(child)?),
+                _ => self.surface(child, node, &mut material)?,
+            }
+        }
+        let base = base.ok_or_else(|| missing(node, "base"))?;
+        let top = top.ok_or_else(|| missing(node, "top"))?;
+        if (top - base).length() == 0.0 {
+            return Err(node.error("`cylinder` base and top must differ"));
+        }
+        Ok(Cylinder::new(
+            base,
+            top,
+            radius.ok_or_else(|| missing(node, "radius"))?,
+            material,
+        ))
+    }
+
+    fn mesh(&self, node: &Node) -> Result<Mesh, SceneError> {
+        node.numbers(0)?;
+        let mut file = None;
+        let mut material = default_material();
+        for child in properties(node) {
+            let child = child.leaf()?;
+            match child.name.as_str() {
+                "file" => file = Some(child),
+                _ => self.surface(child, node, &mut material)?,
+            }
+        }
+
+        let file = file.ok_or_else(|| missing(node, "file"))?;
+        let path = self.base_dir.join(file.text()?);
+        obj::load(&path, material).map_err(|err| {
+            SceneError::at(
+                file.args[0].pos,
+                format!("cannot load `{}`: {}", path.display(), err),
+            )
+        })
+    }
+
+    // Surface properties shared by every shape
+    fn surface(&self, child: &Node, node: &Node, material: &mut Arc<Material>) -> Result<(), SceneError> {
+        match child.name.as_str() {
+            "color" => {
+                *material = Arc::new(Material::Lambertian {
+                    albedo: Texture::Solid(color(child)?),
+                })
+            }
+            "texture" => {
+                *material = Arc::new(Material::Lambertian {
+                    albedo: self.lookup_texture(child)?,
+                })
+            }
+            "material" => *material = self.lookup_material(child)?,
+            // Read separately by `transform`
+            name if TRANSFORMS.contains(&name) => {}
+            _ => return Err(unknown(child, Some(node))),
+        }
+        Ok(())
+    }
+
+    // `background r g b`, or a block for gradients and environment maps.
+    // Environment maps also light the scene, so they come with a light.
+    fn background(&self, node: &Node) -> Result<(Background, Option<Light>), SceneError> {
+        if node.children.is_empty() {
+            return Ok((Background::Solid(color(node)?), None));
+        }
+        node.numbers(0)?;
+        let kind = block_type(
+            node,
+            "background",
+            "solid",
+            &[
+                ("solid", &["color"]),
+                ("gradient", &["bottom", "top"]),
+                ("environment", &["file", "intensity", "rotation", "samples"]),
+            ],
+        )?;
+
+        let mut color = Color::black();
+        let mut bottom = Color::white();
+        let mut top = Color::new(0.5, 0.7, 1.0);
+        let mut file = None;
+        let mut intensity = 1.0;
+        let mut rotation = 0.0;
+        let mut samples = 16;
+        for child in &node.children {
+            let child = child.leaf()?;
+            match child.name.as_str() {
+                "color" => color = self::color(child)?,
+                "bottom" => bottom = self::color(child)?,
+                "top" => top = self::color(child)?,
+                "file" => file = Some(child),
+                "intensity" => intensity = non_negative(child)?,
+                "rotation" => rotation = child.number()?,
+                "samples" => {
+                    samples = count(child)?;
+                    if samples == 0 {
+                        return Err(SceneError::at(child.args[0].pos, "`samples` must be at least 1"));
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        Ok(match kind {
+            "solid" => (Background::Solid(color), None),
+            "gradient" => (Background::Gradient { bottom, top }, None),
+            _ => {
+                let image = self.image(file.ok_or_else(|| missing(node, "file"))?, ColorSpace::Srgb)?;
+                let map = Arc::new(EnvironmentMap::new(image, intensity, rotation));
+                let light = Light::new(
+                    LightKind::Environment(map.clone()),
+                    Color::white(),
+                    Falloff::None,
+                    samples,
+                );
+                (Background::Environment(map), Some(light))
+            }
+        })
+    }
+
+    fn light(&self, node: &Node) -> Result<Light, SceneError> {
+        node.numbers(0)?;
+        let kind = block_type(
+            node,
+            "light",
+            "point",
+            &[
+                ("point", &["position", "color", "intensity", "falloff"]),
+                ("directional", &["direction", "color", "intensity"]),
+                (
+                    "spot",
+                    &["position", "direction", "angle", "blend", "color", "intensity", "falloff"],
+                ),
+                (
+                    "rect",
+                    &["corner", "edge_u", "edge_v", "color", "intensity", "falloff", "samples"],
+                ),
+                (
+                    "sphere",
+                    &["position", "radius", "color", "intensity", "falloff", "samples"],
+                ),
+            ],
+        )?;
+
+        let mut position = None;
+        let mut direction = None;
+        let mut angle = 45.0;
+        let mut blend = 0.15;
+        let mut corner = None;
+        let mut edge_u = None;
+        let mut edge_v = None;
+        let mut radius = None;
+        let mut color = Color::white();
+        let mut intensity = 1.0;
+        let mut falloff = Falloff::None;
+        let mut samples = 16;
+        for child in &node.children {
+            let child = child.leaf()?;
+            match child.name.as_str() {
+                "position" => position = Some(point(child)?),
+                "direction" => direction = Some(self::direction(child)?.normalize()),
+                "angle" => {
+                    angle = positive(child)?;
+                    if angle > 180.0 {
+                        return Err(SceneError::at(child.args[0].pos, "`angle` must be at most 180 degrees"));
+                    }
+                }
+                "blend" => {
+                    blend = non_negative(child)?;
+                    if blend > 1.0 {
+                        return Err(SceneError::at(child.args[0].pos, "`blend` must be at most 1"));
+                    }
+                }
+                "corner" => corner = Some(point(child)?),
+                "edge_u" => edge_u = Some(self::direction(child)?),
+                "edge_v" => edge_v = Some(self::direction(child)?),
+                "radius" => radius = Some(positive(child)?),
+                "color" => color = self::color(child)?,
+                "intensity" => intensity = non_negative(child)?,
+                "falloff" => {
+                    falloff = match child.text()? {
+                        "none" => Falloff::None,
+                        "inverse_square" => Falloff::InverseSquare,
+                        other => {
+                            return Err(SceneError::at(
+                                child.args[0].pos,
+                                format!("unknown falloff `{}` (expected none or inverse_square)", other),
+                            ))
+                        }
+                    }
+                }
+                "samples" => {
+                    samples = count(child)?;
+                    if samples == 0 {
+                        return Err(SceneError::at(child.args[0].pos, "`samples` must be at least 1"));
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let position = || position.ok_or_else(|| missing(node, "position"));
+        let direction = || direction.ok_or_else(|| missing(node, "direction"));
+        let light_kind = match kind {
+            "point" => LightKind::Point { position: position()? },
+            "directional" => LightKind::Directional {
+                direction: direction()?,
+            },
+            "spot" => {
+                // `angle` is the full cone; `blend` is the fraction of it
+                // over which the light fades out
+                let outer = (angle / 2.0_f64).to_radians();
+                LightKind::Spot {
+                    position: position()?,
+                    direction: direction()?,
+                    cos_inner: (outer * (1.0 - blend)).cos(),
+                    cos_outer: outer.cos(),
+                }
+            }
+            "rect" => LightKind::Rect {
+                corner: corner.ok_or_else(|| missing(node, "corner"))?,
+                edge_u: edge_u.ok_or_else(|| missing(node, "edge_u"))?,
+                edge_v: edge_v.ok_or_else(|| missing(node, "edge_v"))?,
+            },
+            _ => LightKind::Sphere {
+                center: position()?,
+                radius: radius.ok_or_else(|| missing(node, "radius"))?,
+            },
+        };
+        Ok(Light::new(light_kind, color * intensity, falloff, samples))
+    }
+
+    // `medium { ... }` fills one closed shape block with smoke or haze. The
+    // shape is only a boundary: its material is ignored and it is not drawn.
+    fn medium(&self, node: &Node) -> Result<Medium, SceneError> {
+        node.numbers(0)?;
+        let mut boundary = None;
+        let mut density = None;
+        let mut albedo = Color::white();
+        let mut phase = Phase::Isotropic;
+        for child in &node.children {
+            match child.name.as_str() {
+                name if SHAPES.contains(&name) => {
+                    if boundary.is_some() {
+                        return Err(child.error("`medium` must contain exactly one shape"));
+                    }
+                    boundary = Some(self.shape(child)?);
+                }
+                "density" => density = Some(positive(child.leaf()?)?),
+                "color" => albedo = color(child.leaf()?)?,
+                "phase" => phase = self::phase(child.leaf()?)?,
+                _ => return Err(unknown(child, Some(node))),
+            }
+        }
+        let boundary = boundary.ok_or_else(|| node.error("`medium` must contain exactly one shape"))?;
+        let density = density.ok_or_else(|| missing(node, "density"))?;
+        Ok(Medium::new(boundary, density, albedo, phase))
+    }
+
+    fn lookup_material(&self, node: &Node) -> Result<Arc<Material>, SceneError> {
+        let name = node.text()?;
+        self.materials
+            .get(name)
+            .cloned()
+            .ok_or_else(|| SceneError::at(node.args[0].pos, format!("unknown material `{}`", name)))
+    }
+
+    // Loads the image named by a `file` property
+    fn image(&self, file: &Node, color_space: ColorSpace) -> Result<Image, SceneError> {
+        let path = self.base_dir.join(file.text()?);
+        image_io::read(&path, color_space).map_err(|err| {
+            SceneError::at(
+                file.args[0].pos,
+                format!("cannot load `{}`: {}", path.display(), err),
+            )
+        })
+    }
+
+    // A texture used as a normal or bump map. Those store data rather than
+    // colors, so images are read linear unless they say otherwise.
+    fn lookup_map(&self, node: &Node) -> Result<Texture, SceneError> {
+        let texture = self.lookup_texture(node)?;
+        let (Texture::Image { scale, wrap, .. }, Some((file, color_space))) =
+            (&texture, self.image_files.get(node.text()?))
+        else {
+            return Ok(texture);
+        };
+        match color_space {
+            Some(ColorSpace::Linear) => Ok(texture),
+            Some(ColorSpace::Srgb) => Err(SceneError::at(
+                node.args[0].pos,
+                format!("`{}` needs a linear texture, not an sRGB one", node.name),
+            )),
+            None => Ok(Texture::Image {
+                image: Arc::new(self.image(file, ColorSpace::Linear)?),
+                scale: *scale,
+                wrap: *wrap,
+            }),
+        }
+    }
+
+    fn lookup_texture(&self, node: &Node) -> Result<Texture, SceneError> {
+        let name = node.text()?;
+        self.textures
+            .get(name)
+            .cloned()
+            .ok_or_else(|| SceneError::at(node.args[0].pos, format!("unknown texture `{}`", name)))
+    }
+}
+
+// Reads the `type` property of a block (falling back to `default`) and
+// checks that every other property is one that type accepts. `types` maps
+// each type name to its allowed properties.
+fn block_type<'a>(
+    node: &'a Node,
+    what: &str,
+    default: &'a str,
+    types: &[(&str, &[&str])],
+) -> Result<&'a str, SceneError> {
+    let kind = node.children.iter().find(|child| child.name == "type");
+    let name = match kind {
+        Some(kind) => kind.leaf()?.text()?,
+        None => default,
+    };
+
+    let Some((_, allowed)) = types.iter().find(|(type_name, _)| *type_name == name) else {
+        let names: Vec<&str> = types.iter().map(|(type_name, _)| *type_name).collect();
+        return Err(SceneError::at(
+            kind.unwrap().args[0].pos,
+            format!("unknown {} type `{}` (expected {})", what, name, names.join(", ")),
+        ));
+    };
+
+    for child in &node.children {
+        let property = child.name.as_str();
+        if property == "type" || allowed.contains(&property) {
+            continue;
+        }
+        if types.iter().any(|(_, properties)| properties.contains(&property)) {
+            return Err(child.error(format!("`{}` does not apply to {} {}s", property, name, what)));
+        }
+        return Err(unknown(child, Some(node)));
+    }
+    Ok(name)
+}
+
+// Every block that adds a shape to the scene
+const SHAPES: [&str; 11] = [
+    "sphere",
+    "plane",
+    "triangle",
+    "box",
+    "disk",
+    "cylinder",
+    "mesh",
+    "instance",
+    "union",
+    "intersection",
+    "difference",
+];
+
+// Properties that move, turn or resize a shape
+const TRANSFORMS: [&str; 7] = ["translate", "rotate", "rotate_x", "rotate_y", "rotate_z", "scale", "matrix"];
+
+// Combines the transform properties of a shape block, applied in the order
+// they are written
+fn transform(node: &Node) -> Result<Transform, SceneError> {
+    let mut transform = Transform::identity();
+    for child in &node.children {
+        let step = match child.name.as_str() {
+            "translate" => Transform::translate(vector(child.leaf()?)?),
+            // `rotate <degrees> <axis x y z>`
+            "rotate" => {
+                let v = child.leaf()?.numbers(4)?;
+                let axis = Vec3::new(v[1], v[2], v[3]);
+                if axis.length() == 0.0 {
+                    return Err(child.error("`rotate` axis must not be a zero vector"));
+                }
+                Transform::rotate(axis, v[0])
+            }
+            "rotate_x" => Transform::rotate_x(child.leaf()?.number()?),
+            "rotate_y" => Transform::rotate_y(child.leaf()?.number()?),
+            "rotate_z" => Transform::rotate_z(child.leaf()?.number()?),
+            "scale" => Transform::scale(scale(child.leaf()?)?),
+            // 16 numbers, row by row
+            "matrix" => {
+                let v = child.leaf()?.numbers(16)?;
+                let mut m = [[0.0; 4]; 4];
+                for (i, value) in v.into_iter().enumerate() {
+                    m[i / 4][i % 4] = value;
+                }
+                Transform::from_matrix(Matrix4::new(m))
+                    .ok_or_else(|| child.error("`matrix` cannot be inverted"))?
+            }
+            _ => continue,
+        };
+        transform = transform.then(&step);
+    }
+    Ok(transform)
+}
+
+// `scale s` or `scale x y z`
+fn scale(node: &Node) -> Result<Vec3, SceneError> {
+    let v = if node.args.len() == 1 {
+        vec![node.number()?; 3]
+    } else {
+        node.numbers(3)?
+    };
+    if v.contains(&0.0) {
+        return Err(node.error("`scale` factors must not be zero"));
+    }
+    Ok(Vec3::new(v[0], v[1], v[2]))
+}
+
+// Properties of a shape block, leaving out the `motion` block read by
+// `motion`
+fn properties(node: &Node) -> impl Iterator<Item = &Node> {
+    node.children.iter().filter(|child| child.name != "motion")
+}
+
+// The optional `motion` block of a shape: one `key <time> { ... }` block
+// per keyframe, each giving the pose at that time
+fn motion(node: &Node) -> Result<Option<Motion>, SceneError> {
+    let Some(block) = node.children.iter().find(|child| child.name == "motion") else {
+        return Ok(None);
+    };
+    block.numbers(0)?;
+    // Each key with the node its scale came from, for errors
+    let mut keys = Vec::new();
+    for key in &block.children {
+        if key.name != "key" {
+            return Err(unknown(key, Some(block)));
+        }
+        let time = key.number()?;
+        let mut pose = Pose::default();
+        let mut scaled = key;
+        for child in &key.children {
+            let child = child.leaf()?;
+            match child.name.as_str() {
+                "translate" => pose.translate = vector(child)?,
+                "rotate_x" => pose.rotate.x = child.number()?,
+                "rotate_y" => pose.rotate.y = child.number()?,
+                "rotate_z" => pose.rotate.z = child.number()?,
+                "scale" => {
+                    pose.scale = scale(child)?;
+                    scaled = child;
+                }
+                _ => return Err(unknown(child, Some(key))),
+            }
+        }
+        keys.push((time, pose, scaled));
+    }
+    if keys.is_empty() {
+        return Err(block.error("`motion` needs at least one `key`"));
+    }
+
+    // Scales are interpolated linearly, so one that flips sign between
+    // keys would pass through zero and flatten the shape
+    keys.sort_by(|a, b| a.0.total_cmp(&b.0));
+    for pair in keys.windows(2) {
+        let ((_, from, _), (_, to, node)) = (&pair[0], &pair[1]);
+        if (0..3).any(|axis| from.scale[axis] * to.scale[axis] < 0.0) {
+            return Err(node.error("`scale` must not change sign between motion keys"));
+        }
+    }
+    Ok(Some(Motion::new(keys.into_iter().map(|(time, pose, _)| (time, pose)).collect())))
+}
+
+fn default_material() -> Arc<Material> {
+    Arc::new(Material::Lambertian {
+        albedo: Texture::Solid(Color::white()),
+    })
+}
+
+fn point(node: &Node) -> Result<Point3, SceneError> {
+    let v = node.numbers(3)?;
+    Ok(Point3::new(v[0], v[1], v[2]))
+}
+
+fn color(node: &Node) -> Result<Color, SceneError> {
+    let v = node.numbers(3)?;
+    Ok(Color::new(v[0], v[1], v[2]))
+}
+
+fn vector(node: &Node) -> Result<Vec3, SceneError> {
+    let v = node.numbers(3)?;
+    Ok(Vec3::new(v[0], v[1], v[2]))
+}
+
+// A vector that will be normalized, so it must not be zero
+fn direction(node: &Node) -> Result<Vec3, SceneError> {
+    let v = vector(node)?;
+    if v.length() == 0.0 {
+        return Err(node.error(format!("`{}` must not be a zero vector", node.name)));
+    }
+    Ok(v)
+}
+
+// The negated comparison makes NaN fail too
+#[allow(clippy::neg_cmp_op_on_partial_ord)]
+fn positive(node: &Node) -> Result<f64, SceneError> {
+    let value = node.number()?;
+    if !(value > 0.0) {
+        return Err(SceneError::at(
+            node.args[0].pos,
+            format!("`{}` must be positive", node.name),
+        ));
+    }
+    Ok(value)
+}
+
+// Like `positive`, but allowing 0
+#[allow(clippy::neg_cmp_op_on_partial_ord)]
+fn non_negative(node: &Node) -> Result<f64, SceneError> {
+    let value = node.number()?;
+    if !(value >= 0.0) {
+        return Err(SceneError::at(
+            node.args[0].pos,
+            format!("`{}` must not be negative", node.name),
+        ));
+    }
+    Ok(value)
+}
+
+fn dimension(node: &Node) -> Result<usize, SceneError> {
+    let value = node.number()?;
+    if value < 2.0 || value.fract() != 0.0 {
+        return Err(SceneError::at(
+            node.args[0].pos,
+            format!("`{}` must be a whole number of at least 2", node.name),
+        ));
+    }
+    Ok(value as usize)
+}
+
+// `filter <type> [radius]`
+fn filter(node: &Node) -> Result<Filter, SceneError> {
+    let Some(kind) = node.args.first() else {
+        return Err(node.error("`filter` expects a filter type"));
+    };
+    if node.args.len() > 2 {
+        return Err(SceneError::at(node.args[2].pos, "`filter` expects a type and an optional radius"));
+    }
+    let radius = match node.args.get(1) {
+        Some(arg) if arg.number()? <= 0.0 => return Err(SceneError::at(arg.pos, "filter radius must be positive")),
+        Some(arg) => Some(arg.number()?),
+        None => None,
+    };
+
+    Ok(match kind.text()? {
+        "box" => Filter::Box {
+            radius: radius.unwrap_or(0.5),
+        },
+        "tent" => Filter::Tent {
+            radius: radius.unwrap_or(1.0),
+        },
+        "gaussian" => Filter::Gaussian {
+            radius: radius.unwrap_or(1.5),
+            alpha: 2.0,
+        },
+        "mitchell" => Filter::Mitchell {
+            radius: radius.unwrap_or(2.0),
+            b: 1.0 / 3.0,
+            c: 1.0 / 3.0,
+        },
+        other => {
+            return Err(SceneError::at(
+                kind.pos,
+                format!("unknown filter `{}` (expected box, tent, gaussian or mitchell)", other),
+            ))
+        }
+    })
+}
+
+// `phase isotropic` or `phase henyey_greenstein <g>`, with g between -1
+// (back scattering) and 1 (forward scattering)
+fn phase(node: &Node) -> Result<Phase, SceneError> {
+    let Some(kind) = node.args.first() else {
+        return Err(node.error("`phase` expects a phase function"));
+    };
+    match kind.text()? {
+        "isotropic" => {
+            node.expect_args(1)?;
+            Ok(Phase::Isotropic)
+        }
+        "henyey_greenstein" => {
+            node.expect_args(2)?;
+            let g = node.args[1].number()?;
+            if g.abs() >= 1.0 {
+                return Err(SceneError::at(node.args[1].pos, "henyey_greenstein `g` must be between -1 and 1"));
+            }
+            Ok(Phase::HenyeyGreenstein { g })
+        }
+        other => Err(SceneError::at(
+            kind.pos,
+            format!("unknown phase function `{}` (expected isotropic or henyey_greenstein)", other),
+        )),
+    }
+}
+
+// `fog { color r g b; density d }`
+fn fog(node: &Node) -> Result<Fog, SceneError> {
+    node.numbers(0)?;
+    let mut fog_color = None;
+    let mut density = None;
+    for child in &node.children {
+        let child = child.leaf()?;
+        match child.name.as_str() {
+            "color" => fog_color = Some(color(child)?),
+            "density" => density = Some(positive(child)?),
+            _ => return Err(unknown(child, Some(node))),
+        }
+    }
+    Ok(Fog {
+        color: fog_color.ok_or_else(|| missing(node, "color"))?,
+        density: density.ok_or_else(|| missing(node, "density"))?,
+    })
+}
+
+// `gamma srgb` or `gamma <exponent>`
+fn gamma(node: &Node) -> Result<Gamma, SceneError> {
+    node.expect_args(1)?;
+    let arg = &node.args[0];
+    match &arg.value {
+        Value::Text(name) if name == "srgb" => Ok(Gamma::Srgb),
+        Value::Text(name) => Err(SceneError::at(
+            arg.pos,
+            format!("unknown gamma `{}` (expected srgb or a number)", name),
+        )),
+        Value::Number(gamma) if *gamma > 0.0 => Ok(Gamma::Power(*gamma)),
+        Value::Number(_) => Err(SceneError::at(arg.pos, "`gamma` must be positive")),
+    }
+}
+
+// `aovs <name> ...`, such as `aovs depth normal albedo`
+fn aovs(node: &Node) -> Result<Vec<Aov>, SceneError> {
+    if node.args.is_empty() {
+        return Err(node.error("`aovs` expects at least one AOV name"));
+    }
+    let mut aovs = Vec::new();
+    for arg in &node.args {
+        let name = arg.text()?;
+        let aov = Aov::from_name(name).ok_or_else(|| {
+            SceneError::at(
+                arg.pos,
+                format!(
+                    "unknown AOV `{}` (expected depth, normal, albedo, object_id, material_id or shadow)",
+                    name
+                ),
+            )
+        })?;
+        if !aovs.contains(&aov) {
+            aovs.push(aov);
+        }
+    }
+    Ok(aovs)
+}
+
+// `frames <first> <last>`, inclusive
+fn frames(node: &Node) -> Result<(u32, u32), SceneError> {
+    node.expect_args(2)?;
+    let whole = |arg: &Arg| {
+        let value = arg.number()?;
+        if value < 0.0 || value.fract() != 0.0 {
+            return Err(SceneError::at(arg.pos, "frame numbers must be whole numbers"));
+        }
+        Ok(value as u32)
+    };
+    let (first, last) = (whole(&node.args[0])?, whole(&node.args[1])?);
+    if last < first {
+        return Err(SceneError::at(node.args[1].pos, "the last frame must not come before the first"));
+    }
+    Ok((first, last))
+}
+
+fn count(node: &Node) -> Result<u32, SceneError> {
+    let value = node.number()?;
+    if value < 0.0 || value.fract() != 0.0 {
+        return Err(SceneError::at(
+            node.args[0].pos,
+            format!("`{}` must be a whole number", node.name),
+        ));
+    }
+    Ok(value as u32)
+}
+
+fn unknown(node: &Node, parent: Option<&Node>) -> SceneError {
+    match parent {
+        Some(parent) => node.error(format!("unknown property `{}` in `{}`", node.name, parent.name)),
+        None => node.error(format!("unknown item `{}`", node.name)),
+    }
+}
+
+fn missing(node: &Node, property: &str) -> SceneError {
+    node.error(format!("`{}` is missing `{}`", node.name, property))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Ray;
+
+    // The error loading `source`, as the command line prints it
+    fn error(source: &str) -> String {
+        match parse(source, Path::new("")) {
+            Err(err) => err.to_string(),
+            Ok(_) => panic!("expected an error loading:\n{}", source),
+        }
+    }
+
+    #[test]
+    fn reads_images_linear_for_normal_and_bump_maps() {
+        // A 1x1 image at half brightness
+        let dir = std::env::temp_dir().join(format!("ray_tracer_maps_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("gray.ppm"), "P3\n1 1\n255\n128 128 128\n").unwrap();
+
+        let source = "
+            texture auto {
+                file \"gray.ppm\"
+            }
+            texture encoded {
+                file \"gray.ppm\"
+                color_space srgb
+            }
+            material bumpy {
+                bump auto
+            }
+            material wrong {
+                bump encoded
+            }";
+        let nodes = parser::parse(source).unwrap();
+        let mut builder = Builder::new(&dir, 0.0);
+        builder.texture(&nodes[0]).unwrap();
+        builder.texture(&nodes[1]).unwrap();
+        let gray = |texture: Texture| texture.value((0.5, 0.5), Point3::origin()).r;
+
+        let uses = &nodes[2].children[0];
+        assert!((gray(builder.lookup_texture(uses).unwrap()) - 0.2158).abs() < 1e-3);
+        assert!((gray(builder.lookup_map(uses).unwrap()) - 128.0 / 255.0).abs() < 1e-9);
+        assert_eq!(
+            builder.material(&nodes[3]).unwrap_err().to_string(),
+            "line 13, column 22: `bump` needs a linear texture, not an sRGB one"
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn rejects_perspective_fov_of_180_degrees_or_more() {
+        assert_eq!(
+            error("camera {\n    fov 180\n}"),
+            "line 2, column 9: `fov` must be less than 180 degrees"
+        );
+        assert!(parse("camera {\n    fov 179\n}", Path::new("")).is_ok());
+        // Fisheye lenses see all around
+        assert!(parse("camera {\n    type fisheye\n    fov 220\n}", Path::new("")).is_ok());
+    }
+
+    #[test]
+    fn rejects_a_negative_environment_intensity() {
+        assert_eq!(
+            error("background {\n    type environment\n    file \"sky.hdr\"\n    intensity -1\n}"),
+            "line 4, column 15: `intensity` must not be negative"
+        );
+    }
+
+    #[test]
+    fn rejects_negative_light_intensity() {
+        assert_eq!(
+            error("light {\n    position 0 5 0\n    intensity -2\n}"),
+            "line 3, column 15: `intensity` must not be negative"
+        );
+        assert!(parse("light {\n    position 0 5 0\n    intensity 0\n}", Path::new("")).is_ok());
+    }
+
+    #[test]
+    fn rejects_out_of_range_roughness_and_spot_cones() {
+        assert_eq!(
+            error("material rough {\n    type metal\n    roughness -0.1\n}"),
+            "line 3, column 15: `roughness` must not be negative"
+        );
+        let spot = |property: &str| {
+            error(&format!(
+                "light {{\n    type spot\n    position 0 5 0\n    direction 0 -1 0\n    {}\n}}",
+                property
+            ))
+        };
+        assert_eq!(spot("angle 200"), "line 5, column 11: `angle` must be at most 180 degrees");
+        assert_eq!(spot("angle -10"), "line 5, column 11: `angle` must be positive");
+        assert_eq!(spot("blend 1.5"), "line 5, column 11: `blend` must be at most 1");
+        assert_eq!(spot("blend -0.5"), "line 5, column 11: `blend` must not be negative");
+    }
+
+    #[test]
+    fn rejects_negative_phong_coefficients() {
+        for coefficient in ["ka", "kd", "ks"] {
+            assert_eq!(
+                error(&format!("material shiny {{\n    type phong\n    {} -1\n}}", coefficient)),
+                format!("line 3, column 8: `{}` must not be negative", coefficient)
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_a_negative_normal_strength() {
+        assert_eq!(
+            error("material flat {\n    normal_strength -1\n}"),
+            "line 2, column 21: `normal_strength` must not be negative"
+        );
+    }
+
+    #[test]
+    fn rejects_radii_that_are_not_positive() {
+        assert_eq!(
+            error("sphere {\n    center 0 0 0\n    radius -1\n}"),
+            "line 3, column 12: `radius` must be positive"
+        );
+        assert_eq!(
+            error("disk {\n    center 0 0 0\n    normal 0 1 0\n    radius 0\n}"),
+            "line 4, column 12: `radius` must be positive"
+        );
+        assert_eq!(
+            error("cylinder {\n    base 0 0 0\n    top 0 1 0\n    radius -0.5\n}"),
+            "line 4, column 12: `radius` must be positive"
+        );
+    }
+
+    #[test]
+    fn numbers_materials_in_declaration_order() {
+        let source = "
+            texture dents {
+                type noise
+            }
+            material first {
+                color 1 0 0
+            }
+            material second {
+                color 0 1 0
+                bump dents
+            }
+            sphere {
+                center 0 0 -5
+                radius 1
+                material second
+            }
+            sphere {
+                center 3 0 -5
+                radius 1
+                material first
+            }
+            sphere {
+                center -3 0 -5
+                radius 1
+                color 0 0 1
+            }
+        ";
+        let scene = parse(source, Path::new("")).unwrap().scene;
+        let id = |x: f64| {
+            let ray = Ray::new(Point3::new(x, 0.0, 0.0), Vec3::new(0.0, 0.0, -1.0));
+            scene.material_id(scene.hit(&ray, 0.0, f64::INFINITY).unwrap().material)
+        };
+        assert_eq!((id(0.0), id(3.0), id(-3.0)), (1, 0, 2));
+    }
+
+    #[test]
+    fn rejects_scales_that_flip_between_motion_keys() {
+        let source = "
+sphere {
+    center 0 0 0
+    radius 1
+    motion {
+        key 1 {
+            scale 1 -1 1
+        }
+        key 0 {
+            scale 2
+        }
+    }
+}";
+        assert_eq!(
+            error(source),
+            "line 7, column 13: `scale` must not change sign between motion keys"
+        );
+        // A key without `scale` has a scale of 1
+        assert_eq!(
+            error("sphere {\n    center 0 0 0\n    radius 1\n    motion {\n        key 0 { scale -1 }\n        key 1\n    }\n}"),
+            "line 6, column 9: `scale` must not change sign between motion keys"
+        );
+    }
+
+    #[test]
+    fn rejects_a_negative_aperture() {
+        assert_eq!(
+            error("camera {\n    aperture -0.1\n    focus_distance 4\n}"),
+            "line 2, column 14: `aperture` must not be negative"
+        );
+    }
+
+    #[test]
+    fn rejects_up_along_the_view_direction() {
+        assert_eq!(
+            error("camera {\n    position 0 0 0\n    look_at 0 5 0\n    up 0 1 0\n}"),
+            "line 4, column 5: `up` must not be parallel to the view direction"
+        );
+        assert_eq!(
+            error("camera {\n    direction 0 -1 0\n}"),
+            "line 1, column 1: `up` must not be parallel to the view direction"
+        );
+        assert_eq!(
+            error("camera {\n    position 1 2 3\n    look_at 1 2 3\n}"),
+            "line 1, column 1: `look_at` must differ from `position`"
+        );
+    }
+}
@@ -0,0 +1,343 @@
+use super::SceneError;
+
+// A scene file is a tree of nodes. Every node is a name followed by
+// arguments on the same line and an optional `{ ... }` block of children:
+//
+//     sphere {
+//         center 0 0 -5
+//         radius 1
+//     }
+//
+// The parser only knows about this syntax; what the names mean is decided
+// by the scene builder.
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+}
+
+#[derive(Debug, Clone)]
+pub enum Value {
+    Number(f64),
+    Text(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct Arg {
+    pub value: Value,
+    pub pos: Position,
+}
+
+#[derive(Debug, Clone)]
+pub struct Node {
+    pub name: String,
+    pub args: Vec<Arg>,
+    pub children: Vec<Node>,
+    pub pos: Position,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    Quoted(String),
+    LBrace,
+    RBrace,
+    Newline,
+    Eof,
+}
+
+struct Lexer<'a> {
+    chars: std::iter::Peekable<std::str::Chars<'a>>,
+    line: usize,
+    column: usize,
+}
+
+impl<'a> Lexer<'a> {
+    fn new(source: &'a str) -> Self {
+        Lexer {
+            chars: source.chars().peekable(),
+            line: 1,
+            column: 1,
+        }
+    }
+
+    fn position(&self) -> Position {
+        Position {
+            line: self.line,
+            column: self.column,
+        }
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.chars.next()?;
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+        Some(c)
+    }
+
+    fn next_token(&mut self) -> Result<(Token, Position), SceneError> {
+        // Skip blanks and comments, but not newlines: they end a node.
+        while let Some(&c) = self.chars.peek() {
+            if c == '#' {
+                while let Some(&c) = self.chars.peek() {
+                    if c == '\n' {
+                        break;
+                    }
+                    self.bump();
+                }
+            } else if c != '\n' && c.is_whitespace() {
+                self.bump();
+            } else {
+                break;
+            }
+        }
+
+        let pos = self.position();
+        let token = match self.chars.peek() {
+            None => Token::Eof,
+            Some('\n') => {
+                self.bump();
+                Token::Newline
+            }
+            Some('{') => {
+                self.bump();
+                Token::LBrace
+            }
+            Some('}') => {
+                self.bump();
+                Token::RBrace
+            }
+            Some('"') => {
+                self.bump();
+                let mut text = String::new();
+                loop {
+                    match self.bump() {
+                        Some('"') => break,
+                        Some('\n') | None => {
+                            return Err(SceneError::at(pos, "unterminated string"));
+                        }
+                        Some(c) => text.push(c),
+                    }
+                }
+                Token::Quoted(text)
+            }
+            Some(_) => {
+                let mut word = String::new();
+                while let Some(&c) = self.chars.peek() {
+                    if c.is_whitespace() || c == '{' || c == '}' || c == '#' || c == '"' {
+                        break;
+                    }
+                    word.push(c);
+                    self.bump();
+                }
+                Token::Word(word)
+            }
+        };
+        Ok((token, pos))
+    }
+}
+
+struct Parser<'a> {
+    lexer: Lexer<'a>,
+    peeked: Option<(Token, Position)>,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&mut self) -> Result<&(Token, Position), SceneError> {
+        if self.peeked.is_none() {
+            self.peeked = Some(self.lexer.next_token()?);
+        }
+        Ok(self.peeked.as_ref().unwrap())
+    }
+
+    fn next(&mut self) -> Result<(Token, Position), SceneError> {
+        match self.peeked.take() {
+            Some(token) => Ok(token),
+            None => self.lexer.next_token(),
+        }
+    }
+
+    // Parses nodes until the closing brace of the enclosing block (or the
+    // end of the file at the top level).
+    fn nodes(&mut self, nested: bool) -> Result<Vec<Node>, SceneError> {
+        let mut nodes = Vec::new();
+        loop {
+            let (token, pos) = self.next()?;
+            match token {
+                Token::Newline => continue,
+                Token::Eof if nested => return Err(SceneError::at(pos, "missing closing `}`")),
+                Token::Eof => return Ok(nodes),
+                Token::RBrace if nested => return Ok(nodes),
+                Token::RBrace => return Err(SceneError::at(pos, "unexpected `}`")),
+                Token::LBrace => return Err(SceneError::at(pos, "expected a name before `{`")),
+                Token::Quoted(_) => return Err(SceneError::at(pos, "expected a name, found a string")),
+                Token::Word(name) => nodes.push(self.node(name, pos)?),
+            }
+        }
+    }
+
+    fn node(&mut self, name: String, pos: Position) -> Result<Node, SceneError> {
+        let mut args = Vec::new();
+        let mut children = Vec::new();
+        loop {
+            match self.peek()?.0 {
+                Token::Newline | Token::Eof | Token::RBrace => break,
+                Token::LBrace => {
+                    self.next()?;
+                    children = self.nodes(true)?;
+                    break;
+                }
+                _ => {}
+            }
+            let (token, pos) = self.next()?;
+            let value = match token {
+                Token::Quoted(text) => Value::Text(text),
+                Token::Word(word) => match word.parse::<f64>() {
+                    // Rust also reads `inf`, `nan` and overflowing
+                    // exponents as numbers
+                    Ok(number) if !number.is_finite() => {
+                        return Err(SceneError::at(pos, format!("`{}` is not a finite number", word)));
+                    }
+                    Ok(number) => Value::Number(number),
+                    Err(_) => Value::Text(word),
+                },
+                _ => unreachable!(),
+            };
+            args.push(Arg { value, pos });
+        }
+        Ok(Node {
+            name,
+            args,
+            children,
+            pos,
+        })
+    }
+}
+
+pub fn parse(source: &str) -> Result<Vec<Node>, SceneError> {
+    let mut parser = Parser {
+        lexer: Lexer::new(source),
+        peeked: None,
+    };
+    parser.nodes(false)
+}
+
+impl Node {
+    pub fn error(&self, message: impl Into<String>) -> SceneError {
+        SceneError::at(self.pos, message)
+    }
+
+    pub fn expect_args(&self, count: usize) -> Result<(), SceneError> {
+        if self.args.len() == count {
+            return Ok(());
+        }
+        let plural = if count == 1 { "" } else { "s" };
+        let pos = self.args.get(count).map_or(self.pos, |arg| arg.pos);
+        Err(SceneError::at(
+            pos,
+            format!(
+                "`{}` expects {} argument{}, found {}",
+                self.name,
+                count,
+                plural,
+                self.args.len()
+            ),
+        ))
+    }
+
+    pub fn numbers(&self, count: usize) -> Result<Vec<f64>, SceneError> {
+        self.expect_args(count)?;
+        self.args.iter().map(|arg| arg.number()).collect()
+    }
+
+    pub fn number(&self) -> Result<f64, SceneError> {
+        Ok(self.numbers(1)?[0])
+    }
+
+    pub fn text(&self) -> Result<&str, SceneError> {
+        self.expect_args(1)?;
+        self.args[0].text()
+    }
+
+    // Rejects a `{ ... }` block on nodes that only take arguments.
+    pub fn leaf(&self) -> Result<&Self, SceneError> {
+        match self.children.first() {
+            Some(child) => Err(child.error(format!("`{}` does not take a block", self.name))),
+            None => Ok(self),
+        }
+    }
+}
+
+impl Arg {
+    pub fn number(&self) -> Result<f64, SceneError> {
+        match &self.value {
+            Value::Number(number) => Ok(*number),
+            Value::Text(text) => Err(SceneError::at(
+                self.pos,
+                format!("expected a number, found `{}`", text),
+            )),
+        }
+    }
+
+    pub fn text(&self) -> Result<&str, SceneError> {
+        match &self.value {
+            Value::Text(text) => Ok(text),
+            Value::Number(_) => Err(SceneError::at(self.pos, "expected a name, found a number")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Line, column and message of the error parsing `source`
+    fn error(source: &str) -> (usize, usize, String) {
+        match parse(source) {
+            Err(SceneError::Syntax { line, column, message }) => (line, column, message),
+            other => panic!("expected a syntax error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn records_positions_of_nodes_and_arguments() {
+        let nodes = parse("# comment\nsphere {\n    radius 2\n}\n").unwrap();
+        assert_eq!(nodes[0].pos, Position { line: 2, column: 1 });
+        let radius = &nodes[0].children[0];
+        assert_eq!(radius.pos, Position { line: 3, column: 5 });
+        assert_eq!(radius.args[0].pos, Position { line: 3, column: 12 });
+    }
+
+    #[test]
+    fn reports_unterminated_strings_where_they_start() {
+        assert_eq!(error("output \"a.png\nwidth 4"), (1, 8, "unterminated string".to_string()));
+    }
+
+    #[test]
+    fn reports_unbalanced_braces() {
+        assert_eq!(error("camera {\n  fov 40\n"), (3, 1, "missing closing `}`".to_string()));
+        assert_eq!(error("ambient 0.1\n}"), (2, 1, "unexpected `}`".to_string()));
+        assert_eq!(error("  {"), (1, 3, "expected a name before `{`".to_string()));
+    }
+
+    #[test]
+    fn rejects_numbers_that_are_not_finite() {
+        assert_eq!(error("radius nan"), (1, 8, "`nan` is not a finite number".to_string()));
+        assert_eq!(error("sphere {\n  radius -inf\n}"), (2, 10, "`-inf` is not a finite number".to_string()));
+        assert_eq!(error("scale 1e999"), (1, 7, "`1e999` is not a finite number".to_string()));
+    }
+
+    #[test]
+    fn reports_bad_arguments_at_the_argument() {
+        let nodes = parse("sphere {\n  radius big\n}").unwrap();
+        let SceneError::Syntax { line, column, .. } = nodes[0].children[0].number().unwrap_err() else {
+            panic!("expected a syntax error");
+        };
+        assert_eq!((line, column), (2, 10));
+    }
+}
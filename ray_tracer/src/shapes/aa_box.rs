@@ -0,0 +1,140 @@
+use crate::aabb::Aabb;
+use crate::hittable::{Hit, Hittable};
+use crate::material::Material;
+use crate::vec3::{Point3, Vec3};
+use crate::Ray;
+use std::sync::Arc;
+
+// Axis-aligned box spanning `min` to `max`. Each face has its own UV
+// square over the face.
+pub struct AaBox {
+    min: Point3,
+    max: Point3,
+    material: Arc<Material>,
+}
+
+impl AaBox {
+    pub fn new(a: Point3, b: Point3, material: Arc<Material>) -> Self {
+        AaBox {
+            min: a.min(&b),
+            max: a.max(&b),
+            material,
+        }
+    }
+}
+
+impl Hittable for AaBox {
+    // Slab test, remembering which axis produced the entry and exit points
+    fn intersect(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<Hit<'_>> {
+        let (origin, direction, min, max) = (ray.origin, ray.direction, self.min, self.max);
+
+        let (mut t_near, mut t_far) = (f64::NEG_INFINITY, f64::INFINITY);
+        let (mut near_axis, mut far_axis) = (0, 0);
+        for axis in 0..3 {
+            let inv = 1.0 / direction[axis];
+            let mut t0 = (min[axis] - origin[axis]) * inv;
+            let mut t1 = (max[axis] - origin[axis]) * inv;
+            if inv < 0.0 {
+                std::mem::swap(&mut t0, &mut t1);
+            }
+            if t0 > t_near {
+                t_near = t0;
+                near_axis = axis;
+            }
+            if t1 < t_far {
+                t_far = t1;
+                far_axis = axis;
+            }
+            if t_near > t_far {
+                return None;
+            }
+        }
+
+        let (t, axis) = if t_near > t_min && t_near < t_max {
+            (t_near, near_axis)
+        } else if t_far > t_min && t_far < t_max {
+            (t_far, far_axis)
+        } else {
+            return None;
+        };
+
+        // Outward normal of the face on `axis` closest to the hit point
+        let coords = ray.point_at(t);
+        let mid = (min[axis] + max[axis]) / 2.0;
+        let sign = if coords[axis] < mid { -1.0 } else { 1.0 };
+        let face_coord = |a: usize| (coords[a] - min[a]) / (max[a] - min[a]).max(f64::MIN_POSITIVE);
+        let (u_axis, v_axis) = ((axis + 1) % 3, (axis + 2) % 3);
+        let uv = (face_coord(u_axis), face_coord(v_axis));
+        let unit = |a: usize| {
+            let mut v = Vec3::zero();
+            v[a] = 1.0;
+            v
+        };
+        Some(
+            Hit::new(ray, t, unit(axis) * sign, uv, &self.material)
+                .with_tangents(unit(u_axis), unit(v_axis)),
+        )
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        Some(Aabb::new(self.min, self.max))
+    }
+
+    fn contains(&self, point: Point3, _time: f64) -> bool {
+        (0..3).all(|axis| self.min[axis] < point[axis] && point[axis] < self.max[axis])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::Color;
+    use crate::texture::Texture;
+
+    // From (-1, 0, -1) to (1, 2, 1)
+    fn cube() -> AaBox {
+        let material = Arc::new(Material::Lambertian {
+            albedo: Texture::Solid(Color::white()),
+        });
+        AaBox::new(Point3::new(1.0, 2.0, 1.0), Point3::new(-1.0, 0.0, -1.0), material)
+    }
+
+    #[test]
+    fn hits_the_face_the_ray_enters() {
+        let cube = cube();
+        let from_front = Ray::new(Point3::new(0.5, 1.0, 5.0), Vec3::new(0.0, 0.0, -1.0));
+        let hit = cube.intersect(&from_front, 0.0, f64::INFINITY).unwrap();
+        assert!((hit.t - 4.0).abs() < 1e-9);
+        assert_eq!(hit.normal, Vec3::new(0.0, 0.0, 1.0));
+        assert!(hit.front_face);
+        // u and v run along the next two axes across the face
+        assert!((hit.uv.0 - 0.75).abs() < 1e-9 && (hit.uv.1 - 0.5).abs() < 1e-9);
+
+        let from_below = Ray::new(Point3::new(0.0, -3.0, 0.0), Vec3::new(0.1, 1.0, 0.0));
+        let hit = cube.intersect(&from_below, 0.0, f64::INFINITY).unwrap();
+        assert_eq!(hit.normal, Vec3::new(0.0, -1.0, 0.0));
+    }
+
+    #[test]
+    fn hits_the_far_face_from_inside() {
+        let cube = cube();
+        let ray = Ray::new(Point3::new(0.0, 1.0, 0.0), Vec3::new(-1.0, 0.0, 0.0));
+        let hit = cube.intersect(&ray, 0.0, f64::INFINITY).unwrap();
+        assert!((hit.t - 1.0).abs() < 1e-9);
+        // Outward is -x, so the normal faces back along +x
+        assert_eq!(hit.normal, Vec3::new(1.0, 0.0, 0.0));
+        assert!(!hit.front_face);
+    }
+
+    #[test]
+    fn grazing_rays_just_inside_hit_and_just_outside_miss() {
+        let cube = cube();
+        let inside = Ray::new(Point3::new(-5.0, 2.0 - 1e-9, 0.0), Vec3::new(1.0, 0.0, 0.0));
+        let hit = cube.intersect(&inside, 0.0, f64::INFINITY).unwrap();
+        assert!((hit.t - 4.0).abs() < 1e-9);
+        assert_eq!(hit.normal, Vec3::new(-1.0, 0.0, 0.0));
+
+        let outside = Ray::new(Point3::new(-5.0, 2.0 + 1e-9, 0.0), Vec3::new(1.0, 0.0, 0.0));
+        assert!(cube.intersect(&outside, 0.0, f64::INFINITY).is_none());
+    }
+}
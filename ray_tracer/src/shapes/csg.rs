@@ -0,0 +1,249 @@
+use crate::aabb::Aabb;
+use crate::hittable::{Hit, Hittable};
+use crate::material::Material;
+use crate::vec3::Point3;
+use crate::Ray;
+use std::sync::Arc;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CsgOperation {
+    // Inside either shape
+    Union,
+    // Inside both shapes
+    Intersection,
+    // Inside the first shape but not the second
+    Difference,
+}
+
+impl CsgOperation {
+    fn contains(self, in_a: bool, in_b: bool) -> bool {
+        match self {
+            CsgOperation::Union => in_a || in_b,
+            CsgOperation::Intersection => in_a && in_b,
+            CsgOperation::Difference => in_a && !in_b,
+        }
+    }
+}
+
+// Constructive solid geometry: two solids combined into one. The ray's
+// crossings of each shape are walked in order, tracking whether it is
+// inside each one, and the combined surface is wherever being inside the
+// result changes. This works for any closed shape, including meshes,
+// instances and other CSG nodes. A plane counts as the half-space behind
+// its normal, since `Hittable::contains` tells where a ray starts even if
+// it never crosses the plane.
+pub struct Csg {
+    operation: CsgOperation,
+    a: Box<dyn Hittable>,
+    b: Box<dyn Hittable>,
+    // Replaces the materials of both shapes if set
+    material: Option<Arc<Material>>,
+}
+
+impl Csg {
+    pub fn new(operation: CsgOperation, a: Box<dyn Hittable>, b: Box<dyn Hittable>) -> Self {
+        Csg {
+            operation,
+            a,
+            b,
+            material: None,
+        }
+    }
+
+    pub fn with_material(mut self, material: Arc<Material>) -> Self {
+        self.material = Some(material);
+        self
+    }
+}
+
+impl Hittable for Csg {
+    fn intersect(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<Hit<'_>> {
+        let mut next_a = self.a.intersect(ray, t_min, t_max);
+        let mut next_b = self.b.intersect(ray, t_min, t_max);
+        // Whether the ray starts inside each shape. Its own next crossing
+        // cannot tell, since it may never cross at all, as when it points
+        // away from a plane it starts behind.
+        let start = ray.point_at(t_min);
+        let mut in_a = self.a.contains(start, ray.time);
+        let mut in_b = self.b.contains(start, ray.time);
+
+        loop {
+            let from_a = match (&next_a, &next_b) {
+                (Some(a), Some(b)) => a.t <= b.t,
+                (Some(_), None) => true,
+                (None, Some(_)) => false,
+                (None, None) => return None,
+            };
+            let was_inside = self.operation.contains(in_a, in_b);
+
+            // Crossings set the state rather than toggling it, so a surface
+            // reported twice (such as a mesh edge) does no harm
+            let hit = if from_a {
+                let hit = next_a.take().unwrap();
+                in_a = hit.front_face;
+                next_a = self.a.intersect(ray, hit.t + 1e-9, t_max);
+                hit
+            } else {
+                let hit = next_b.take().unwrap();
+                in_b = hit.front_face;
+                next_b = self.b.intersect(ray, hit.t + 1e-9, t_max);
+                hit
+            };
+
+            let inside = self.operation.contains(in_a, in_b);
+            if inside != was_inside {
+                // The normal already faces the ray; only which side is
+                // outside can change, as on the inner wall of a hole
+                let mut hit = hit;
+                hit.front_face = inside;
+                if let Some(material) = &self.material {
+                    hit.material = material;
+                }
+                return Some(hit);
+            }
+        }
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let (a, b) = (self.a.bounding_box(), self.b.bounding_box());
+        match self.operation {
+            CsgOperation::Union => Some(a?.union(&b?)),
+            CsgOperation::Intersection => match (a, b) {
+                (Some(a), Some(b)) => Some(a.intersection(&b)),
+                (a, b) => a.or(b),
+            },
+            CsgOperation::Difference => a,
+        }
+    }
+
+    fn contains(&self, point: Point3, time: f64) -> bool {
+        self.operation.contains(self.a.contains(point, time), self.b.contains(point, time))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::Color;
+    use crate::shapes::{Plane, Sphere};
+    use crate::texture::Texture;
+    use crate::vec3::Vec3;
+
+    fn material() -> Arc<Material> {
+        Arc::new(Material::Lambertian {
+            albedo: Texture::Solid(Color::white()),
+        })
+    }
+
+    fn sphere(x: f64, radius: f64) -> Box<dyn Hittable> {
+        Box::new(Sphere::new(Point3::new(x, 0.0, 0.0), radius, material()))
+    }
+
+    // Everything below y = 0
+    fn ground() -> Box<dyn Hittable> {
+        Box::new(Plane::new(Point3::origin(), Vec3::new(0.0, 1.0, 0.0), material()))
+    }
+
+    fn ray(origin: Point3, direction: Vec3) -> Ray {
+        Ray::new(origin, direction)
+    }
+
+    fn hit_t(csg: &Csg, ray: &Ray) -> Option<f64> {
+        csg.intersect(ray, 0.0, f64::INFINITY).map(|hit| hit.t)
+    }
+
+    #[test]
+    fn union_skips_the_overlap() {
+        let csg = Csg::new(CsgOperation::Union, sphere(-0.5, 1.0), sphere(0.5, 1.0));
+        let along_x = ray(Point3::new(-5.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0));
+        let hit = csg.intersect(&along_x, 0.0, f64::INFINITY).unwrap();
+        assert!((hit.t - 3.5).abs() < 1e-9);
+        assert!(hit.front_face);
+        // The next surface is where the second sphere ends, not the first
+        let exit = csg.intersect(&along_x, hit.t + 1e-6, f64::INFINITY).unwrap();
+        assert!((exit.t - 6.5).abs() < 1e-9);
+        assert!(!exit.front_face);
+    }
+
+    #[test]
+    fn union_from_inside_one_shape() {
+        let csg = Csg::new(CsgOperation::Union, sphere(0.0, 1.0), sphere(5.0, 1.0));
+        let from_center = ray(Point3::origin(), Vec3::new(-1.0, 0.0, 0.0));
+        let hit = csg.intersect(&from_center, 0.0, f64::INFINITY).unwrap();
+        assert!((hit.t - 1.0).abs() < 1e-9);
+        assert!(!hit.front_face);
+    }
+
+    #[test]
+    fn intersection_keeps_the_overlap() {
+        let csg = Csg::new(CsgOperation::Intersection, sphere(-0.5, 1.0), sphere(0.5, 1.0));
+        let along_x = ray(Point3::new(-5.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0));
+        assert!((hit_t(&csg, &along_x).unwrap() - 4.5).abs() < 1e-9);
+        let above = ray(Point3::new(-5.0, 1.5, 0.0), Vec3::new(1.0, 0.0, 0.0));
+        assert_eq!(hit_t(&csg, &above), None);
+    }
+
+    #[test]
+    fn intersection_with_a_plane_it_starts_behind() {
+        // Inside the lower half of the sphere, heading down and away from
+        // the plane, which it never crosses
+        let csg = Csg::new(CsgOperation::Intersection, sphere(0.0, 1.0), ground());
+        let down = ray(Point3::new(0.0, -0.5, 0.0), Vec3::new(0.0, -1.0, 0.0));
+        let hit = csg.intersect(&down, 0.0, f64::INFINITY).unwrap();
+        assert!((hit.t - 0.5).abs() < 1e-9);
+        assert!(!hit.front_face);
+    }
+
+    #[test]
+    fn intersection_with_a_plane_facing_down() {
+        // Everything above y = 0; the ray starts inside the upper half of
+        // the sphere and heads away from the plane
+        let ceiling = Box::new(Plane::new(Point3::origin(), Vec3::new(0.0, -1.0, 0.0), material()));
+        let csg = Csg::new(CsgOperation::Intersection, sphere(0.0, 1.0), ceiling);
+        let up = ray(Point3::new(0.0, 0.5, 0.0), Vec3::new(0.0, 1.0, 0.0));
+        let hit = csg.intersect(&up, 0.0, f64::INFINITY).unwrap();
+        assert!((hit.t - 0.5).abs() < 1e-9);
+        assert!(!hit.front_face);
+    }
+
+    #[test]
+    fn nested_operations_know_where_rays_start() {
+        // The upper half of a sphere with a smaller sphere removed from it,
+        // entered from inside the hollow
+        let ceiling = Box::new(Plane::new(Point3::origin(), Vec3::new(0.0, -1.0, 0.0), material()));
+        let dome = Csg::new(CsgOperation::Intersection, sphere(0.0, 2.0), ceiling);
+        let csg = Csg::new(CsgOperation::Difference, Box::new(dome), sphere(0.0, 1.0));
+        let up = ray(Point3::new(0.0, 0.5, 0.0), Vec3::new(0.0, 1.0, 0.0));
+        let hit = csg.intersect(&up, 0.0, f64::INFINITY).unwrap();
+        assert!((hit.t - 0.5).abs() < 1e-9);
+        assert!(hit.front_face);
+        assert!(csg.contains(Point3::new(0.0, 1.5, 0.0), 0.0));
+        assert!(!csg.contains(Point3::new(0.0, -1.5, 0.0), 0.0));
+    }
+
+    #[test]
+    fn difference_removes_the_second_shape() {
+        // The upper half of a sphere
+        let csg = Csg::new(CsgOperation::Difference, sphere(0.0, 1.0), ground());
+        let down = ray(Point3::new(0.0, 5.0, 0.0), Vec3::new(0.0, -1.0, 0.0));
+        let hit = csg.intersect(&down, 0.0, f64::INFINITY).unwrap();
+        assert!((hit.t - 4.0).abs() < 1e-9);
+        assert!(hit.front_face);
+        // Leaving the solid through the cut face
+        let exit = csg.intersect(&down, hit.t + 1e-6, f64::INFINITY).unwrap();
+        assert!((exit.t - 5.0).abs() < 1e-9);
+        assert!(!exit.front_face);
+    }
+
+    #[test]
+    fn difference_from_inside_the_removed_shape() {
+        // Starts in the removed lower half and only leaves the sphere, so
+        // it never enters the solid
+        let csg = Csg::new(CsgOperation::Difference, sphere(0.0, 1.0), ground());
+        let down = ray(Point3::new(0.0, -0.5, 0.0), Vec3::new(0.0, -1.0, 0.0));
+        assert_eq!(hit_t(&csg, &down), None);
+        // Past the sphere, outside both shapes
+        let sideways = ray(Point3::new(0.0, -0.5, 0.0), Vec3::new(1.0, 0.0, 0.0));
+        assert_eq!(hit_t(&csg, &sideways), None);
+    }
+}
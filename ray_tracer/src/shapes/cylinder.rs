@@ -0,0 +1,166 @@
+use super::disk::{disk_bounds, planar_uv};
+use crate::aabb::Aabb;
+use crate::hittable::{Hit, Hittable};
+use crate::material::Material;
+use crate::sampling::orthonormal_basis;
+use crate::vec3::{Point3, Vec3};
+use crate::Ray;
+use std::f64::consts::PI;
+use std::sync::Arc;
+
+// Capped cylinder between the centers of its two end caps. On the side, u
+// goes around the axis and v from base to top; caps are mapped like disks.
+pub struct Cylinder {
+    base: Point3,
+    axis: Vec3,
+    // Perpendicular to `axis`, for measuring angles around it
+    axes: (Vec3, Vec3),
+    height: f64,
+    radius: f64,
+    material: Arc<Material>,
+}
+
+impl Cylinder {
+    pub fn new(base: Point3, top: Point3, radius: f64, material: Arc<Material>) -> Self {
+        let axis = top - base;
+        Cylinder {
+            base,
+            axis: axis.normalize(),
+            axes: orthonormal_basis(axis.normalize()),
+            height: axis.length(),
+            radius,
+            material,
+        }
+    }
+}
+
+impl Hittable for Cylinder {
+    fn intersect(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<Hit<'_>> {
+        let mut closest: Option<Hit> = None;
+        let mut consider = |t: f64, normal: Vec3, uv: (f64, f64), (tangent, bitangent): (Vec3, Vec3)| {
+            if t > t_min && t < t_max && closest.is_none_or(|best| t < best.t) {
+                closest = Some(Hit::new(ray, t, normal, uv, &self.material).with_tangents(tangent, bitangent));
+            }
+        };
+
+        // Side: solve the circle equation in the plane perpendicular to the axis
+        let oc = ray.origin - self.base;
+        let d_perp = ray.direction - self.axis * ray.direction.dot(&self.axis);
+        let o_perp = oc - self.axis * oc.dot(&self.axis);
+        let a = d_perp.dot(&d_perp);
+        let b = 2.0 * o_perp.dot(&d_perp);
+        let c = o_perp.dot(&o_perp) - self.radius * self.radius;
+        let discriminant = b * b - 4.0 * a * c;
+        if a > 1e-12 && discriminant >= 0.0 {
+            let sqrt_d = discriminant.sqrt();
+            for t in [(-b - sqrt_d) / (2.0 * a), (-b + sqrt_d) / (2.0 * a)] {
+                let height = (oc + ray.direction * t).dot(&self.axis);
+                if (0.0..=self.height).contains(&height) {
+                    let normal = (o_perp + d_perp * t) / self.radius;
+                    let (x, y) = (normal.dot(&self.axes.0), normal.dot(&self.axes.1));
+                    let uv = ((y.atan2(x) + PI) / (2.0 * PI), height / self.height);
+                    // u increases around the axis and v along it
+                    let tangent = self.axes.1 * x - self.axes.0 * y;
+                    consider(t, normal, uv, (tangent, self.axis));
+                }
+            }
+        }
+
+        // Caps
+        let denom = ray.direction.dot(&self.axis);
+        if denom.abs() > 1e-9 {
+            for (height, normal) in [(0.0, -self.axis), (self.height, self.axis)] {
+                let t = (height - oc.dot(&self.axis)) / denom;
+                let offset = oc + ray.direction * t - self.axis * height;
+                if offset.dot(&offset) <= self.radius * self.radius {
+                    consider(t, normal, planar_uv(offset, self.axes, self.radius), self.axes);
+                }
+            }
+        }
+
+        closest
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let top = self.base + self.axis * self.height;
+        Some(disk_bounds(self.base, self.axis, self.radius).union(&disk_bounds(top, self.axis, self.radius)))
+    }
+
+    fn contains(&self, point: Point3, _time: f64) -> bool {
+        let offset = point - self.base;
+        let along = offset.dot(&self.axis);
+        let across = offset - self.axis * along;
+        0.0 < along && along < self.height && across.length() < self.radius
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::Color;
+    use crate::texture::Texture;
+
+    // Radius 1 around the y axis, from y = 0 to y = 2
+    fn cylinder() -> Cylinder {
+        let material = Arc::new(Material::Lambertian {
+            albedo: Texture::Solid(Color::white()),
+        });
+        Cylinder::new(Point3::origin(), Point3::new(0.0, 2.0, 0.0), 1.0, material)
+    }
+
+    fn assert_close(a: Vec3, b: Vec3) {
+        assert!((a - b).length() < 1e-9, "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn hits_the_side_and_the_caps() {
+        let cylinder = cylinder();
+        let side = Ray::new(Point3::new(-5.0, 1.0, 0.0), Vec3::new(1.0, 0.0, 0.0));
+        let hit = cylinder.intersect(&side, 0.0, f64::INFINITY).unwrap();
+        assert!((hit.t - 4.0).abs() < 1e-9);
+        assert_close(hit.normal, Vec3::new(-1.0, 0.0, 0.0));
+        assert!(hit.front_face);
+        assert!((hit.uv.1 - 0.5).abs() < 1e-9);
+
+        let top = Ray::new(Point3::new(0.5, 5.0, 0.0), Vec3::new(0.0, -1.0, 0.0));
+        let hit = cylinder.intersect(&top, 0.0, f64::INFINITY).unwrap();
+        assert!((hit.t - 3.0).abs() < 1e-9);
+        assert_close(hit.normal, Vec3::new(0.0, 1.0, 0.0));
+
+        // Past the end, the side is open air
+        let beyond = Ray::new(Point3::new(-5.0, 2.5, 0.0), Vec3::new(1.0, 0.0, 0.0));
+        assert!(cylinder.intersect(&beyond, 0.0, f64::INFINITY).is_none());
+    }
+
+    #[test]
+    fn hits_the_inside_from_within() {
+        let cylinder = cylinder();
+        let sideways = Ray::new(Point3::new(0.0, 1.0, 0.0), Vec3::new(0.0, 0.0, 1.0));
+        let hit = cylinder.intersect(&sideways, 0.0, f64::INFINITY).unwrap();
+        assert!((hit.t - 1.0).abs() < 1e-9);
+        assert_close(hit.normal, Vec3::new(0.0, 0.0, -1.0));
+        assert!(!hit.front_face);
+
+        let down = Ray::new(Point3::new(0.0, 1.5, 0.0), Vec3::new(0.0, -1.0, 0.0));
+        let hit = cylinder.intersect(&down, 0.0, f64::INFINITY).unwrap();
+        assert!((hit.t - 1.5).abs() < 1e-9);
+        assert_close(hit.normal, Vec3::new(0.0, 1.0, 0.0));
+        assert!(!hit.front_face);
+    }
+
+    #[test]
+    fn grazing_rays_touch_the_side() {
+        let cylinder = cylinder();
+        let across = Ray::new(Point3::new(-5.0, 1.0, 1.0), Vec3::new(1.0, 0.0, 0.0));
+        let hit = cylinder.intersect(&across, 0.0, f64::INFINITY).unwrap();
+        assert!((hit.t - 5.0).abs() < 1e-9);
+        assert!(hit.normal.dot(&across.direction).abs() < 1e-9);
+
+        // Parallel to the axis along the side: only the rims of the caps
+        let along = Ray::new(Point3::new(1.0, 5.0, 0.0), Vec3::new(0.0, -1.0, 0.0));
+        let hit = cylinder.intersect(&along, 0.0, f64::INFINITY).unwrap();
+        assert!((hit.t - 3.0).abs() < 1e-9);
+        let outside = Ray::new(Point3::new(1.0 + 1e-6, 5.0, 0.0), Vec3::new(0.0, -1.0, 0.0));
+        assert!(cylinder.intersect(&outside, 0.0, f64::INFINITY).is_none());
+    }
+}
@@ -0,0 +1,126 @@
+use crate::aabb::Aabb;
+use crate::hittable::{Hit, Hittable};
+use crate::material::Material;
+use crate::sampling::orthonormal_basis;
+use crate::vec3::{Point3, Vec3};
+use crate::Ray;
+use std::sync::Arc;
+
+// Flat circle: the part of a plane within `radius` of `center`. The UV
+// square [0, 1]² covers the disk's bounding square.
+pub struct Disk {
+    center: Point3,
+    normal: Vec3,
+    axes: (Vec3, Vec3),
+    radius: f64,
+    material: Arc<Material>,
+}
+
+impl Disk {
+    pub fn new(center: Point3, normal: Vec3, radius: f64, material: Arc<Material>) -> Self {
+        let normal = normal.normalize();
+        Disk {
+            center,
+            normal,
+            axes: orthonormal_basis(normal),
+            radius,
+            material,
+        }
+    }
+}
+
+impl Hittable for Disk {
+    fn intersect(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<Hit<'_>> {
+        let denom = self.normal.dot(&ray.direction);
+        if denom.abs() < 1e-9 {
+            return None;
+        }
+
+        let t = (self.center - ray.origin).dot(&self.normal) / denom;
+        if t <= t_min || t >= t_max {
+            return None;
+        }
+
+        let offset = ray.point_at(t) - self.center;
+        if offset.dot(&offset) > self.radius * self.radius {
+            return None;
+        }
+        let uv = planar_uv(offset, self.axes, self.radius);
+        Some(Hit::new(ray, t, self.normal, uv, &self.material))
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        Some(disk_bounds(self.center, self.normal, self.radius))
+    }
+
+    // Flat, so nothing is inside
+    fn contains(&self, _point: Point3, _time: f64) -> bool {
+        false
+    }
+}
+
+// Maps an offset from a disk's center to UVs covering its bounding square
+pub fn planar_uv(offset: Vec3, axes: (Vec3, Vec3), radius: f64) -> (f64, f64) {
+    (
+        0.5 + offset.dot(&axes.0) / (2.0 * radius),
+        0.5 + offset.dot(&axes.1) / (2.0 * radius),
+    )
+}
+
+// Tight bounds of a disk: along each axis it extends `radius` times the
+// sine of the angle between that axis and the normal.
+pub fn disk_bounds(center: Point3, normal: Vec3, radius: f64) -> Aabb {
+    let extent = Vec3::new(
+        radius * (1.0 - normal.x * normal.x).max(0.0).sqrt(),
+        radius * (1.0 - normal.y * normal.y).max(0.0).sqrt(),
+        radius * (1.0 - normal.z * normal.z).max(0.0).sqrt(),
+    );
+    Aabb::new(center - extent, center + extent)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::Color;
+    use crate::texture::Texture;
+
+    // Radius 2 around (0, 1, 0), facing up
+    fn disk() -> Disk {
+        let material = Arc::new(Material::Lambertian {
+            albedo: Texture::Solid(Color::white()),
+        });
+        Disk::new(Point3::new(0.0, 1.0, 0.0), Vec3::new(0.0, 3.0, 0.0), 2.0, material)
+    }
+
+    #[test]
+    fn hits_within_the_radius_from_either_side() {
+        let disk = disk();
+        let down = Ray::new(Point3::new(1.0, 4.0, 1.0), Vec3::new(0.0, -1.0, 0.0));
+        let hit = disk.intersect(&down, 0.0, f64::INFINITY).unwrap();
+        assert!((hit.t - 3.0).abs() < 1e-9);
+        assert_eq!(hit.normal, Vec3::new(0.0, 1.0, 0.0));
+        assert!(hit.front_face);
+
+        let up = Ray::new(Point3::new(1.0, -1.0, 1.0), Vec3::new(0.0, 1.0, 0.0));
+        let hit = disk.intersect(&up, 0.0, f64::INFINITY).unwrap();
+        assert!((hit.t - 2.0).abs() < 1e-9);
+        assert_eq!(hit.normal, Vec3::new(0.0, -1.0, 0.0));
+        assert!(!hit.front_face);
+    }
+
+    #[test]
+    fn misses_outside_the_radius_and_along_the_plane() {
+        let disk = disk();
+        let outside = Ray::new(Point3::new(1.5, 4.0, 1.5), Vec3::new(0.0, -1.0, 0.0));
+        assert!(disk.intersect(&outside, 0.0, f64::INFINITY).is_none());
+        // The rim itself still counts
+        let rim = Ray::new(Point3::new(2.0, 4.0, 0.0), Vec3::new(0.0, -1.0, 0.0));
+        assert!(disk.intersect(&rim, 0.0, f64::INFINITY).is_some());
+
+        let grazing = Ray::new(Point3::new(-5.0, 1.0, 0.0), Vec3::new(1.0, 0.0, 0.0));
+        assert!(disk.intersect(&grazing, 0.0, f64::INFINITY).is_none());
+        // Starting on the disk, a ray leaves it without hitting it again
+        let leaving = Ray::new(Point3::new(0.0, 1.0, 0.0), Vec3::new(0.0, 1.0, 0.0));
+        assert!(disk.intersect(&leaving, 1e-9, f64::INFINITY).is_none());
+    }
+}
@@ -0,0 +1,88 @@
+use crate::aabb::Aabb;
+use crate::hittable::{Hit, Hittable};
+use crate::motion::Motion;
+use crate::transform::Transform;
+use crate::vec3::Point3;
+use crate::Ray;
+use std::sync::Arc;
+
+// An object placed in the scene by a transform, and optionally moving over
+// time. Rays are moved into the object's own space instead of moving the
+// object, and the object is shared, so one mesh can appear many times with
+// a single copy in memory.
+pub struct Instance {
+    object: Arc<dyn Hittable>,
+    to_world: Transform,
+    to_object: Transform,
+    // Applied after `to_world`, at the time of each ray
+    motion: Option<Motion>,
+    bounds: Option<Aabb>,
+}
+
+impl Instance {
+    pub fn new(object: Arc<dyn Hittable>, transform: Transform) -> Self {
+        let bounds = object.bounding_box().map(|b| transform.bounds(&b));
+        Instance {
+            object,
+            to_world: transform,
+            to_object: transform.inverse(),
+            motion: None,
+            bounds,
+        }
+    }
+
+    pub fn with_motion(mut self, motion: Motion) -> Self {
+        self.bounds = self.bounds.map(|b| motion.bounds(&b));
+        self.motion = Some(motion);
+        self
+    }
+
+    fn intersect_with(
+        &self,
+        ray: &Ray,
+        t_min: f64,
+        t_max: f64,
+        to_world: &Transform,
+        to_object: &Transform,
+    ) -> Option<Hit<'_>> {
+        // The object-space ray is normalized again, so distances along it
+        // are stretched by the length of the transformed direction
+        let direction = to_object.vector(ray.direction);
+        let stretch = direction.length();
+        let local = Ray::new(to_object.point(ray.origin), direction).with_time(ray.time);
+
+        let mut hit = self.object.intersect(&local, t_min * stretch, t_max * stretch)?;
+        hit.t /= stretch;
+        hit.point = ray.point_at(hit.t);
+        // Orientation relative to the ray survives the transform, so
+        // `front_face` stays valid
+        hit.normal = to_world.normal(hit.normal).normalize();
+        hit.tangent = to_world.vector(hit.tangent).normalize();
+        hit.bitangent = to_world.vector(hit.bitangent).normalize();
+        Some(hit)
+    }
+}
+
+impl Hittable for Instance {
+    fn intersect(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<Hit<'_>> {
+        match &self.motion {
+            Some(motion) => {
+                let to_world = self.to_world.then(&motion.at(ray.time));
+                self.intersect_with(ray, t_min, t_max, &to_world, &to_world.inverse())
+            }
+            None => self.intersect_with(ray, t_min, t_max, &self.to_world, &self.to_object),
+        }
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        self.bounds
+    }
+
+    fn contains(&self, point: Point3, time: f64) -> bool {
+        let to_object = match &self.motion {
+            Some(motion) => self.to_world.then(&motion.at(time)).inverse(),
+            None => self.to_object,
+        };
+        self.object.contains(to_object.point(point), time)
+    }
+}
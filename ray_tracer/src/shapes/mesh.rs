@@ -0,0 +1,122 @@
+use super::triangle::intersect_triangle;
+use crate::aabb::Aabb;
+use crate::bvh::Bvh;
+use crate::hittable::{Hit, Hittable};
+use crate::material::Material;
+use crate::vec3::{Point3, Vec3};
+use crate::Ray;
+use std::sync::Arc;
+
+// Indices into the mesh's position, normal and texture coordinate lists
+pub struct MeshTriangle {
+    pub positions: [usize; 3],
+    pub normals: Option<[usize; 3]>,
+    pub uvs: Option<[usize; 3]>,
+}
+
+// Indexed triangle mesh. Triangles with per-vertex normals are smooth
+// shaded by interpolating them; the rest use the flat face normal.
+// Texture coordinates are interpolated the same way, falling back to the
+// barycentric coordinates. Each mesh keeps its own BVH over its triangles.
+pub struct Mesh {
+    positions: Vec<Point3>,
+    normals: Vec<Vec3>,
+    uvs: Vec<(f64, f64)>,
+    triangles: Vec<MeshTriangle>,
+    bvh: Bvh,
+    material: Arc<Material>,
+}
+
+impl Mesh {
+    pub fn new(
+        positions: Vec<Point3>,
+        normals: Vec<Vec3>,
+        uvs: Vec<(f64, f64)>,
+        triangles: Vec<MeshTriangle>,
+        material: Arc<Material>,
+    ) -> Self {
+        let bounds: Vec<Aabb> = triangles
+            .iter()
+            .map(|triangle| Aabb::from_points(&triangle.positions.map(|i| positions[i])))
+            .collect();
+        Mesh {
+            bvh: Bvh::build(&bounds),
+            positions,
+            normals,
+            uvs,
+            triangles,
+            material,
+        }
+    }
+
+    fn vertices(&self, triangle: &MeshTriangle) -> [Point3; 3] {
+        triangle.positions.map(|i| self.positions[i])
+    }
+
+    fn normal(&self, triangle: &MeshTriangle, u: f64, v: f64) -> Vec3 {
+        match triangle.normals {
+            Some([n0, n1, n2]) => (self.normals[n0] * (1.0 - u - v)
+                + self.normals[n1] * u
+                + self.normals[n2] * v)
+            .normalize(),
+            None => {
+                let [v0, v1, v2] = self.vertices(triangle);
+                (v1 - v0).cross(&(v2 - v0)).normalize()
+            }
+        }
+    }
+
+    fn uv(&self, triangle: &MeshTriangle, u: f64, v: f64) -> (f64, f64) {
+        match triangle.uvs {
+            Some([t0, t1, t2]) => {
+                let w = 1.0 - u - v;
+                let (a, b, c) = (self.uvs[t0], self.uvs[t1], self.uvs[t2]);
+                (w * a.0 + u * b.0 + v * c.0, w * a.1 + u * b.1 + v * c.1)
+            }
+            None => (u, v),
+        }
+    }
+
+    // Directions of increasing u and v across the triangle, from its edges
+    // and texture coordinates
+    fn tangents(&self, triangle: &MeshTriangle) -> (Vec3, Vec3) {
+        let [p0, p1, p2] = self.vertices(triangle);
+        let (e1, e2) = (p1 - p0, p2 - p0);
+        let [t0, t1, t2] = [(0.0, 0.0), (1.0, 0.0), (0.0, 1.0)].map(|(u, v)| self.uv(triangle, u, v));
+        let (du1, dv1) = (t1.0 - t0.0, t1.1 - t0.1);
+        let (du2, dv2) = (t2.0 - t0.0, t2.1 - t0.1);
+        let det = du1 * dv2 - du2 * dv1;
+        if det.abs() < 1e-12 {
+            return (e1, e2);
+        }
+        (
+            (e1 * dv2 - e2 * dv1) / det,
+            (e2 * du1 - e1 * du2) / det,
+        )
+    }
+}
+
+impl Hittable for Mesh {
+    fn intersect(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<Hit<'_>> {
+        let (t, u, v, triangle) = self.bvh.closest(ray, t_min, t_max, |i, limit| {
+            let triangle = &self.triangles[i];
+            let (t, u, v) = intersect_triangle(ray, self.vertices(triangle), t_min, limit)?;
+            Some((t, (t, u, v, triangle)))
+        })?;
+        let (tangent, bitangent) = self.tangents(triangle);
+        Some(
+            Hit::new(
+                ray,
+                t,
+                self.normal(triangle, u, v),
+                self.uv(triangle, u, v),
+                &self.material,
+            )
+            .with_tangents(tangent, bitangent),
+        )
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        self.bvh.bounds()
+    }
+}
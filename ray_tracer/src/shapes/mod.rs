@@ -0,0 +1,21 @@
+// Geometric primitives
+
+mod aa_box;
+mod csg;
+mod cylinder;
+mod disk;
+mod instance;
+mod mesh;
+mod plane;
+mod sphere;
+mod triangle;
+
+pub use aa_box::AaBox;
+pub use csg::{Csg, CsgOperation};
+pub use cylinder::Cylinder;
+pub use disk::Disk;
+pub use instance::Instance;
+pub use mesh::{Mesh, MeshTriangle};
+pub use plane::Plane;
+pub use sphere::Sphere;
+pub use triangle::Triangle;
@@ -0,0 +1,95 @@
+use crate::aabb::Aabb;
+use crate::hittable::{Hit, Hittable};
+use crate::material::Material;
+use crate::sampling::orthonormal_basis;
+use crate::vec3::{Point3, Vec3};
+use crate::Ray;
+use std::sync::Arc;
+
+// Infinite plane through `point`, visible from both sides. UV coordinates
+// are distances from `point` in world units along two in-plane axes.
+pub struct Plane {
+    point: Point3,
+    normal: Vec3,
+    axes: (Vec3, Vec3),
+    material: Arc<Material>,
+}
+
+impl Plane {
+    pub fn new(point: Point3, normal: Vec3, material: Arc<Material>) -> Self {
+        let normal = normal.normalize();
+        Plane {
+            point,
+            normal,
+            axes: orthonormal_basis(normal),
+            material,
+        }
+    }
+}
+
+impl Hittable for Plane {
+    fn intersect(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<Hit<'_>> {
+        let denom = self.normal.dot(&ray.direction);
+        if denom.abs() < 1e-9 {
+            return None;
+        }
+
+        let t = (self.point - ray.origin).dot(&self.normal) / denom;
+        if t <= t_min || t >= t_max {
+            return None;
+        }
+        let offset = ray.point_at(t) - self.point;
+        let uv = (offset.dot(&self.axes.0), offset.dot(&self.axes.1));
+        Some(Hit::new(ray, t, self.normal, uv, &self.material))
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        None
+    }
+
+    // The half-space behind the normal
+    fn contains(&self, point: Point3, _time: f64) -> bool {
+        (point - self.point).dot(&self.normal) < 0.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::Color;
+    use crate::texture::Texture;
+
+    // The y = -1 floor
+    fn floor() -> Plane {
+        let material = Arc::new(Material::Lambertian {
+            albedo: Texture::Solid(Color::white()),
+        });
+        Plane::new(Point3::new(0.0, -1.0, 0.0), Vec3::new(0.0, 2.0, 0.0), material)
+    }
+
+    #[test]
+    fn hits_from_above_and_below() {
+        let floor = floor();
+        let down = Ray::new(Point3::new(3.0, 1.0, 0.0), Vec3::new(0.0, -1.0, -1.0));
+        let hit = floor.intersect(&down, 0.0, f64::INFINITY).unwrap();
+        assert!((hit.t - 8f64.sqrt()).abs() < 1e-9);
+        assert!((hit.point.y + 1.0).abs() < 1e-9);
+        assert_eq!(hit.normal, Vec3::new(0.0, 1.0, 0.0));
+        assert!(hit.front_face);
+
+        let up = Ray::new(Point3::new(0.0, -3.0, 0.0), Vec3::new(0.0, 1.0, 0.0));
+        let hit = floor.intersect(&up, 0.0, f64::INFINITY).unwrap();
+        assert!((hit.t - 2.0).abs() < 1e-9);
+        assert_eq!(hit.normal, Vec3::new(0.0, -1.0, 0.0));
+        assert!(!hit.front_face);
+    }
+
+    #[test]
+    fn misses_parallel_and_receding_rays() {
+        let floor = floor();
+        let parallel = Ray::new(Point3::new(0.0, -1.0, 0.0), Vec3::new(1.0, 0.0, 0.0));
+        assert!(floor.intersect(&parallel, 0.0, f64::INFINITY).is_none());
+        let away = Ray::new(Point3::new(0.0, 1.0, 0.0), Vec3::new(0.0, 1.0, 0.0));
+        assert!(floor.intersect(&away, 0.0, f64::INFINITY).is_none());
+    }
+}
@@ -0,0 +1,129 @@
+use crate::aabb::Aabb;
+use crate::hittable::{Hit, Hittable};
+use crate::material::Material;
+use crate::vec3::{Point3, Vec3};
+use crate::Ray;
+use std::f64::consts::PI;
+use std::sync::Arc;
+
+pub struct Sphere {
+    center: Point3,
+    radius: f64,
+    material: Arc<Material>,
+}
+
+impl Sphere {
+    pub fn new(center: Point3, radius: f64, material: Arc<Material>) -> Self {
+        Sphere {
+            center,
+            radius,
+            material,
+        }
+    }
+
+    fn normal_at(&self, point: Point3) -> Vec3 {
+        (point - self.center).normalize()
+    }
+}
+
+// Longitude and latitude of a point on the unit sphere; v runs from the
+// bottom pole (0) to the top (1)
+fn uv_at(normal: Vec3) -> (f64, f64) {
+    let phi = (-normal.z).atan2(normal.x) + PI;
+    let theta = (-normal.y).clamp(-1.0, 1.0).acos();
+    (phi / (2.0 * PI), theta / PI)
+}
+
+impl Hittable for Sphere {
+    fn intersect(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<Hit<'_>> {
+        let oc = ray.origin - self.center;
+        let a = ray.direction.dot(&ray.direction);
+        let b = 2.0 * oc.dot(&ray.direction);
+        let c = oc.dot(&oc) - self.radius * self.radius;
+        let discriminant = b * b - 4.0 * a * c;
+
+        if discriminant < 0.0 {
+            return None;
+        }
+
+        // Try the nearer root first, then the farther one
+        let sqrt_d = discriminant.sqrt();
+        [(-b - sqrt_d) / (2.0 * a), (-b + sqrt_d) / (2.0 * a)]
+            .into_iter()
+            .find(|&t| t > t_min && t < t_max)
+            .map(|t| {
+                let normal = self.normal_at(ray.point_at(t));
+                let hit = Hit::new(ray, t, normal, uv_at(normal), &self.material);
+                // Longitude direction; undefined at the poles
+                let tangent = Vec3::new(normal.z, 0.0, -normal.x);
+                if tangent.length() < 1e-9 {
+                    hit
+                } else {
+                    hit.with_tangents(tangent, normal.cross(&tangent))
+                }
+            })
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let r = Vec3::new(self.radius, self.radius, self.radius);
+        Some(Aabb::new(self.center - r, self.center + r))
+    }
+
+    fn contains(&self, point: Point3, _time: f64) -> bool {
+        (point - self.center).length() < self.radius
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::Color;
+    use crate::texture::Texture;
+
+    fn unit_sphere() -> Sphere {
+        let material = Arc::new(Material::Lambertian {
+            albedo: Texture::Solid(Color::white()),
+        });
+        Sphere::new(Point3::new(0.0, 0.0, -5.0), 1.0, material)
+    }
+
+    fn assert_close(a: Vec3, b: Vec3) {
+        assert!((a - b).length() < 1e-9, "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn hits_the_near_side_from_outside() {
+        let sphere = unit_sphere();
+        let ray = Ray::new(Point3::origin(), Vec3::new(0.0, 0.0, -1.0));
+        let hit = sphere.intersect(&ray, 0.0, f64::INFINITY).unwrap();
+        assert!((hit.t - 4.0).abs() < 1e-9);
+        assert!((hit.point - Point3::new(0.0, 0.0, -4.0)).length() < 1e-9);
+        assert_close(hit.normal, Vec3::new(0.0, 0.0, 1.0));
+        assert!(hit.front_face);
+        // Cut off before the surface
+        assert!(sphere.intersect(&ray, 0.0, 3.9).is_none());
+    }
+
+    #[test]
+    fn hits_the_far_side_from_inside() {
+        let sphere = unit_sphere();
+        let ray = Ray::new(Point3::new(0.0, 0.5, -5.0), Vec3::new(0.0, 1.0, 0.0));
+        let hit = sphere.intersect(&ray, 0.0, f64::INFINITY).unwrap();
+        assert!((hit.t - 0.5).abs() < 1e-9);
+        // Outward is +y, so the normal faces back down the ray
+        assert_close(hit.normal, Vec3::new(0.0, -1.0, 0.0));
+        assert!(!hit.front_face);
+    }
+
+    #[test]
+    fn grazing_rays_touch_the_silhouette() {
+        let sphere = unit_sphere();
+        let touching = Ray::new(Point3::new(-5.0, 1.0, -5.0), Vec3::new(1.0, 0.0, 0.0));
+        let hit = sphere.intersect(&touching, 0.0, f64::INFINITY).unwrap();
+        assert!((hit.t - 5.0).abs() < 1e-9);
+        assert!(hit.normal.dot(&touching.direction).abs() < 1e-9);
+
+        let above = Ray::new(Point3::new(-5.0, 1.0 + 1e-6, -5.0), Vec3::new(1.0, 0.0, 0.0));
+        assert!(sphere.intersect(&above, 0.0, f64::INFINITY).is_none());
+    }
+}
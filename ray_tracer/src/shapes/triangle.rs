@@ -0,0 +1,123 @@
+use crate::aabb::Aabb;
+use crate::hittable::{Hit, Hittable};
+use crate::material::Material;
+use crate::vec3::Point3;
+use crate::Ray;
+use std::sync::Arc;
+
+pub struct Triangle {
+    v0: Point3,
+    v1: Point3,
+    v2: Point3,
+    material: Arc<Material>,
+}
+
+impl Triangle {
+    pub fn new(v0: Point3, v1: Point3, v2: Point3, material: Arc<Material>) -> Self {
+        Triangle {
+            v0,
+            v1,
+            v2,
+            material,
+        }
+    }
+}
+
+impl Hittable for Triangle {
+    fn intersect(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<Hit<'_>> {
+        let (t, u, v) = intersect_triangle(ray, [self.v0, self.v1, self.v2], t_min, t_max)?;
+        let normal = (self.v1 - self.v0).cross(&(self.v2 - self.v0)).normalize();
+        // Barycentric coordinates double as UVs
+        Some(Hit::new(ray, t, normal, (u, v), &self.material).with_tangents(self.v1 - self.v0, self.v2 - self.v0))
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        Some(Aabb::from_points(&[self.v0, self.v1, self.v2]))
+    }
+
+    // Flat, so nothing is inside
+    fn contains(&self, _point: Point3, _time: f64) -> bool {
+        false
+    }
+}
+
+// Möller–Trumbore intersection. Returns the distance along the ray and the
+// barycentric weights of the second and third vertices.
+pub fn intersect_triangle(ray: &Ray, v: [Point3; 3], t_min: f64, t_max: f64) -> Option<(f64, f64, f64)> {
+    let edge1 = v[1] - v[0];
+    let edge2 = v[2] - v[0];
+    let p = ray.direction.cross(&edge2);
+    let det = edge1.dot(&p);
+    if det.abs() < 1e-12 {
+        return None;
+    }
+
+    let inv_det = 1.0 / det;
+    let s = ray.origin - v[0];
+    let u = s.dot(&p) * inv_det;
+    if !(0.0..=1.0).contains(&u) {
+        return None;
+    }
+
+    let q = s.cross(&edge1);
+    let v = ray.direction.dot(&q) * inv_det;
+    if v < 0.0 || u + v > 1.0 {
+        return None;
+    }
+
+    let t = edge2.dot(&q) * inv_det;
+    if t <= t_min || t >= t_max {
+        return None;
+    }
+    Some((t, u, v))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::Color;
+    use crate::texture::Texture;
+    use crate::vec3::Vec3;
+
+    // Right triangle in the z = -2 plane, wound to face +z
+    fn triangle() -> Triangle {
+        let material = Arc::new(Material::Lambertian {
+            albedo: Texture::Solid(Color::white()),
+        });
+        Triangle::new(
+            Point3::new(0.0, 0.0, -2.0),
+            Point3::new(1.0, 0.0, -2.0),
+            Point3::new(0.0, 1.0, -2.0),
+            material,
+        )
+    }
+
+    #[test]
+    fn hits_inside_with_barycentric_uvs() {
+        let triangle = triangle();
+        let ray = Ray::new(Point3::new(0.25, 0.5, 0.0), Vec3::new(0.0, 0.0, -1.0));
+        let hit = triangle.intersect(&ray, 0.0, f64::INFINITY).unwrap();
+        assert!((hit.t - 2.0).abs() < 1e-9);
+        assert_eq!(hit.normal, Vec3::new(0.0, 0.0, 1.0));
+        assert!(hit.front_face);
+        assert!((hit.uv.0 - 0.25).abs() < 1e-9 && (hit.uv.1 - 0.5).abs() < 1e-9);
+
+        // Two-sided, facing back at a ray from behind
+        let behind = Ray::new(Point3::new(0.25, 0.25, -4.0), Vec3::new(0.0, 0.0, 1.0));
+        let hit = triangle.intersect(&behind, 0.0, f64::INFINITY).unwrap();
+        assert_eq!(hit.normal, Vec3::new(0.0, 0.0, -1.0));
+        assert!(!hit.front_face);
+    }
+
+    #[test]
+    fn edges_count_and_grazing_rays_miss() {
+        let triangle = triangle();
+        let on_hypotenuse = Ray::new(Point3::new(0.5, 0.5, 0.0), Vec3::new(0.0, 0.0, -1.0));
+        assert!(triangle.intersect(&on_hypotenuse, 0.0, f64::INFINITY).is_some());
+        let outside = Ray::new(Point3::new(0.5, 0.51, 0.0), Vec3::new(0.0, 0.0, -1.0));
+        assert!(triangle.intersect(&outside, 0.0, f64::INFINITY).is_none());
+
+        let in_plane = Ray::new(Point3::new(-1.0, 0.25, -2.0), Vec3::new(1.0, 0.0, 0.0));
+        assert!(triangle.intersect(&in_plane, 0.0, f64::INFINITY).is_none());
+    }
+}
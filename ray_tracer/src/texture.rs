@@ -0,0 +1,214 @@
+// Surface color patterns
+//
+// Image, checker and gradient textures are looked up by the hit's UV
+// coordinates. Noise and marble are solid textures evaluated at the hit
+// point in world space, so they do not depend on how a shape is mapped.
+
+use crate::color::Color;
+use crate::image::Image;
+use crate::rng::Rng;
+use crate::vec3::{Point3, Vec3};
+use std::sync::Arc;
+
+#[derive(Debug, Clone)]
+pub enum Texture {
+    Solid(Color),
+    // Alternating squares, `scale` of them per unit of UV
+    Checker { even: Color, odd: Color, scale: f64 },
+    // Linear blend from `from` at 0 to `to` at 1 along one UV coordinate
+    Gradient { from: Color, to: Color, axis: UvAxis },
+    // Fractal Perlin noise; `scale` sets the feature frequency in world units
+    Noise { color: Color, scale: f64, noise: Arc<Perlin> },
+    // Sine bands along x, `scale` radians per world unit, distorted by
+    // turbulence
+    Marble {
+        color: Color,
+        vein: Color,
+        scale: f64,
+        turbulence: f64,
+        noise: Arc<Perlin>,
+    },
+    // Bilinearly filtered image, repeated `scale` times per unit of UV
+    Image { image: Arc<Image>, scale: f64, wrap: Wrap },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UvAxis {
+    U,
+    V,
+}
+
+// What image lookups do outside the [0, 1] UV square
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Wrap {
+    Repeat,
+    Clamp,
+    Mirror,
+}
+
+impl Texture {
+    pub fn value(&self, uv: (f64, f64), point: Point3) -> Color {
+        match self {
+            Texture::Solid(color) => *color,
+            Texture::Checker { even, odd, scale } => {
+                let cell = (uv.0 * scale).floor() as i64 + (uv.1 * scale).floor() as i64;
+                if cell.rem_euclid(2) == 0 {
+                    *even
+                } else {
+                    *odd
+                }
+            }
+            Texture::Gradient { from, to, axis } => {
+                let t = match axis {
+                    UvAxis::U => uv.0,
+                    UvAxis::V => uv.1,
+                };
+                from.lerp(to, t.clamp(0.0, 1.0))
+            }
+            Texture::Noise { color, scale, noise } => {
+                let value = noise.fractal(Vec3::from(point) * *scale, 7);
+                *color * (0.5 * (1.0 + value.clamp(-1.0, 1.0)))
+            }
+            Texture::Marble {
+                color,
+                vein,
+                scale,
+                turbulence,
+                noise,
+            } => {
+                let phase = scale * point.x + turbulence * noise.turbulence(Vec3::from(point), 7);
+                color.lerp(vein, 0.5 * (1.0 + phase.sin()))
+            }
+            Texture::Image { image, scale, wrap } => bilinear(image, uv.0 * scale, uv.1 * scale, *wrap),
+        }
+    }
+}
+
+// Blends the four texels around (u, v). Texel centers sit at half-integer
+// positions and v = 0 is the bottom row.
+fn bilinear(image: &Image, u: f64, v: f64, wrap: Wrap) -> Color {
+    let x = u * image.width as f64 - 0.5;
+    let y = (1.0 - v) * image.height as f64 - 0.5;
+    let (x0, y0) = (x.floor(), y.floor());
+    let (fx, fy) = (x - x0, y - y0);
+
+    let texel = |dx: i64, dy: i64| {
+        let tx = wrap_index(x0 as i64 + dx, image.width, wrap);
+        let ty = wrap_index(y0 as i64 + dy, image.height, wrap);
+        image.get(tx, ty)
+    };
+    let top = texel(0, 0).lerp(&texel(1, 0), fx);
+    let bottom = texel(0, 1).lerp(&texel(1, 1), fx);
+    top.lerp(&bottom, fy)
+}
+
+fn wrap_index(i: i64, size: usize, wrap: Wrap) -> usize {
+    let n = size as i64;
+    let i = match wrap {
+        Wrap::Repeat => i.rem_euclid(n),
+        Wrap::Clamp => i.clamp(0, n - 1),
+        Wrap::Mirror => {
+            let m = i.rem_euclid(2 * n);
+            if m >= n {
+                2 * n - 1 - m
+            } else {
+                m
+            }
+        }
+    };
+    i as usize
+}
+
+// Ken Perlin's improved gradient noise, with a permutation table shuffled
+// from a seed so different textures can have different patterns
+#[derive(Debug)]
+pub struct Perlin {
+    permutation: Vec<usize>,
+}
+
+impl Perlin {
+    pub fn new(seed: u64) -> Self {
+        let mut rng = Rng::new(seed);
+        let mut table: Vec<usize> = (0..256).collect();
+        for i in (1..table.len()).rev() {
+            let j = (rng.next_u64() % (i as u64 + 1)) as usize;
+            table.swap(i, j);
+        }
+        // Doubled so corner hashes never need wrapping
+        let permutation = table.iter().chain(table.iter()).copied().collect();
+        Perlin { permutation }
+    }
+
+    // Smooth noise in roughly [-1, 1]
+    pub fn noise(&self, p: Vec3) -> f64 {
+        let (fx, fy, fz) = (p.x.floor(), p.y.floor(), p.z.floor());
+        let (x, y, z) = (p.x - fx, p.y - fy, p.z - fz);
+        let xi = (fx as i64 & 255) as usize;
+        let yi = (fy as i64 & 255) as usize;
+        let zi = (fz as i64 & 255) as usize;
+        let (u, v, w) = (fade(x), fade(y), fade(z));
+
+        let perm = &self.permutation;
+        let hash = |dx: usize, dy: usize, dz: usize| perm[perm[perm[xi + dx] + yi + dy] + zi + dz];
+        let corner = |dx: usize, dy: usize, dz: usize| {
+            gradient(hash(dx, dy, dz), x - dx as f64, y - dy as f64, z - dz as f64)
+        };
+
+        let mix = |a: f64, b: f64, t: f64| a + t * (b - a);
+        mix(
+            mix(
+                mix(corner(0, 0, 0), corner(1, 0, 0), u),
+                mix(corner(0, 1, 0), corner(1, 1, 0), u),
+                v,
+            ),
+            mix(
+                mix(corner(0, 0, 1), corner(1, 0, 1), u),
+                mix(corner(0, 1, 1), corner(1, 1, 1), u),
+                v,
+            ),
+            w,
+        )
+    }
+
+    // Sum of `octaves` layers of noise, each twice the frequency and half
+    // the amplitude of the last
+    pub fn fractal(&self, p: Vec3, octaves: u32) -> f64 {
+        let (mut sum, mut weight, mut p) = (0.0, 1.0, p);
+        for _ in 0..octaves {
+            sum += weight * self.noise(p);
+            weight *= 0.5;
+            p *= 2.0;
+        }
+        sum
+    }
+
+    // Like `fractal`, but summing absolute values, which gives the sharp
+    // creases used for marble veins
+    pub fn turbulence(&self, p: Vec3, octaves: u32) -> f64 {
+        let (mut sum, mut weight, mut p) = (0.0, 1.0, p);
+        for _ in 0..octaves {
+            sum += weight * self.noise(p).abs();
+            weight *= 0.5;
+            p *= 2.0;
+        }
+        sum
+    }
+}
+
+fn fade(t: f64) -> f64 {
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+// Dot product with one of 12 gradient directions picked by the hash
+fn gradient(hash: usize, x: f64, y: f64, z: f64) -> f64 {
+    let h = hash & 15;
+    let u = if h < 8 { x } else { y };
+    let v = if h < 4 {
+        y
+    } else if h == 12 || h == 14 {
+        x
+    } else {
+        z
+    };
+    (if h & 1 == 0 { u } else { -u }) + (if h & 2 == 0 { v } else { -v })
+}
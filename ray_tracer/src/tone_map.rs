@@ -0,0 +1,167 @@
+// Display transform applied before writing integer images
+//
+// Rendering produces linear radiance with no upper bound. Exposure scales
+// it, a tone curve compresses it into [0, 1] and an encoding curve maps
+// the result to the values a display expects. Float formats such as EXR
+// skip all of this and keep the linear values.
+
+use crate::color::Color;
+use crate::image::Image;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ToneMap {
+    // Clip each channel at 1
+    Clamp,
+    // x / (1 + x) per channel; never quite reaches white
+    Reinhard,
+    // Krzysztof Narkowicz's fit of the ACES filmic curve, with a gentle toe
+    // and shoulder
+    Aces,
+}
+
+impl ToneMap {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "clamp" => Some(ToneMap::Clamp),
+            "reinhard" => Some(ToneMap::Reinhard),
+            "aces" => Some(ToneMap::Aces),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Gamma {
+    // The piecewise sRGB curve
+    Srgb,
+    // Plain power curve, `value^(1/gamma)`; 1 writes linear values
+    Power(f64),
+}
+
+impl Gamma {
+    // `srgb` or a positive exponent
+    pub fn from_name(name: &str) -> Option<Self> {
+        if name == "srgb" {
+            return Some(Gamma::Srgb);
+        }
+        name.parse::<f64>()
+            .ok()
+            .filter(|gamma| gamma.is_finite() && *gamma > 0.0)
+            .map(Gamma::Power)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct OutputTransform {
+    // In stops: every +1 doubles the brightness
+    pub exposure: f64,
+    pub tone_map: ToneMap,
+    pub gamma: Gamma,
+}
+
+impl Default for OutputTransform {
+    // Clips to [0, 1] and encodes as sRGB, which is what displays and
+    // image viewers expect from 8 and 16-bit images
+    fn default() -> Self {
+        OutputTransform {
+            exposure: 0.0,
+            tone_map: ToneMap::Clamp,
+            gamma: Gamma::Srgb,
+        }
+    }
+}
+
+impl OutputTransform {
+    // Maps a linear color to display values in [0, 1]
+    pub fn apply(&self, color: Color) -> Color {
+        let color = color * 2f64.powf(self.exposure);
+        color.map(|x| {
+            let x = x.max(0.0);
+            let mapped = match self.tone_map {
+                ToneMap::Clamp => x,
+                ToneMap::Reinhard => x / (1.0 + x),
+                ToneMap::Aces => (x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14),
+            };
+            encode(mapped.min(1.0), self.gamma)
+        })
+    }
+
+    pub fn apply_image(&self, image: &Image) -> Image {
+        Image {
+            width: image.width,
+            height: image.height,
+            pixels: image.pixels.iter().map(|&color| self.apply(color)).collect(),
+        }
+    }
+}
+
+fn encode(x: f64, gamma: Gamma) -> f64 {
+    match gamma {
+        Gamma::Srgb if x <= 0.0031308 => 12.92 * x,
+        Gamma::Srgb => 1.055 * x.powf(1.0 / 2.4) - 0.055,
+        Gamma::Power(gamma) => x.powf(1.0 / gamma),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Tone curve alone, with no encoding
+    fn curve(tone_map: ToneMap, exposure: f64, x: f64) -> f64 {
+        let transform = OutputTransform {
+            exposure,
+            tone_map,
+            gamma: Gamma::Power(1.0),
+        };
+        transform.apply(Color::new(x, x, x)).r
+    }
+
+    fn assert_close(a: f64, b: f64) {
+        assert!((a - b).abs() < 1e-4, "{} != {}", a, b);
+    }
+
+    #[test]
+    fn tone_curves() {
+        assert_eq!(curve(ToneMap::Clamp, 0.0, 0.5), 0.5);
+        assert_eq!(curve(ToneMap::Clamp, 0.0, 2.0), 1.0);
+        assert_eq!(curve(ToneMap::Clamp, 0.0, -1.0), 0.0);
+        assert_eq!(curve(ToneMap::Reinhard, 0.0, 1.0), 0.5);
+        assert_eq!(curve(ToneMap::Reinhard, 0.0, 3.0), 0.75);
+        assert_eq!(curve(ToneMap::Aces, 0.0, 0.0), 0.0);
+        assert_close(curve(ToneMap::Aces, 0.0, 1.0), 2.54 / 3.16);
+        // The ACES fit overshoots 1 for bright values and is clipped
+        assert_eq!(curve(ToneMap::Aces, 0.0, 100.0), 1.0);
+    }
+
+    #[test]
+    fn exposure_is_in_stops() {
+        assert_eq!(curve(ToneMap::Clamp, 1.0, 0.25), 0.5);
+        assert_eq!(curve(ToneMap::Clamp, -2.0, 1.0), 0.25);
+        assert_close(curve(ToneMap::Reinhard, 1.0, 1.0), 2.0 / 3.0);
+    }
+
+    #[test]
+    fn encoding_curves() {
+        assert_close(encode(0.5, Gamma::Srgb), 0.7354);
+        assert_close(encode(0.002, Gamma::Srgb), 0.02584);
+        assert_close(encode(1.0, Gamma::Srgb), 1.0);
+        assert_close(encode(0.25, Gamma::Power(2.0)), 0.5);
+
+        // The default clips and encodes as sRGB, per channel
+        let color = OutputTransform::default().apply(Color::new(0.5, 4.0, -1.0));
+        assert_close(color.r, 0.7354);
+        assert_close(color.g, 1.0);
+        assert_eq!(color.b, 0.0);
+    }
+
+    #[test]
+    fn parses_names() {
+        assert_eq!(ToneMap::from_name("aces"), Some(ToneMap::Aces));
+        assert_eq!(ToneMap::from_name("filmic"), None);
+        assert_eq!(Gamma::from_name("srgb"), Some(Gamma::Srgb));
+        assert_eq!(Gamma::from_name("2.2"), Some(Gamma::Power(2.2)));
+        assert_eq!(Gamma::from_name("0"), None);
+        assert_eq!(Gamma::from_name("inf"), None);
+    }
+}
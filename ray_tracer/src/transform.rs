@@ -0,0 +1,277 @@
+// Affine transforms
+//
+// A `Transform` keeps its matrix together with the inverse, so moving rays
+// into object space never needs a matrix inversion. Transforms built from
+// translations, rotations and scales compose their exact inverses; only an
+// arbitrary matrix is inverted numerically.
+
+use crate::aabb::Aabb;
+use crate::vec3::{Point3, Vec3};
+use std::ops::Mul;
+
+// Row-major 4x4 matrix acting on column vectors
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Matrix4 {
+    pub m: [[f64; 4]; 4],
+}
+
+impl Matrix4 {
+    pub fn new(m: [[f64; 4]; 4]) -> Self {
+        Matrix4 { m }
+    }
+
+    pub fn identity() -> Self {
+        let mut m = [[0.0; 4]; 4];
+        for (i, row) in m.iter_mut().enumerate() {
+            row[i] = 1.0;
+        }
+        Matrix4 { m }
+    }
+
+    pub fn transpose(&self) -> Matrix4 {
+        let mut m = [[0.0; 4]; 4];
+        for (i, row) in m.iter_mut().enumerate() {
+            for (j, value) in row.iter_mut().enumerate() {
+                *value = self.m[j][i];
+            }
+        }
+        Matrix4 { m }
+    }
+
+    // Gauss-Jordan elimination with partial pivoting. Returns `None` if
+    // the matrix is singular.
+    pub fn inverse(&self) -> Option<Matrix4> {
+        let mut a = self.m;
+        let mut inv = Matrix4::identity().m;
+        for col in 0..4 {
+            let pivot = (col..4).max_by(|&i, &j| a[i][col].abs().total_cmp(&a[j][col].abs()))?;
+            if a[pivot][col].abs() < 1e-12 {
+                return None;
+            }
+            a.swap(col, pivot);
+            inv.swap(col, pivot);
+
+            let scale = 1.0 / a[col][col];
+            for j in 0..4 {
+                a[col][j] *= scale;
+                inv[col][j] *= scale;
+            }
+            for row in 0..4 {
+                if row == col {
+                    continue;
+                }
+                let factor = a[row][col];
+                for j in 0..4 {
+                    a[row][j] -= factor * a[col][j];
+                    inv[row][j] -= factor * inv[col][j];
+                }
+            }
+        }
+        Some(Matrix4 { m: inv })
+    }
+
+    pub fn transform_point(&self, p: Point3) -> Point3 {
+        let m = &self.m;
+        let x = m[0][0] * p.x + m[0][1] * p.y + m[0][2] * p.z + m[0][3];
+        let y = m[1][0] * p.x + m[1][1] * p.y + m[1][2] * p.z + m[1][3];
+        let z = m[2][0] * p.x + m[2][1] * p.y + m[2][2] * p.z + m[2][3];
+        let w = m[3][0] * p.x + m[3][1] * p.y + m[3][2] * p.z + m[3][3];
+        if w == 1.0 {
+            Point3::new(x, y, z)
+        } else {
+            Point3::new(x / w, y / w, z / w)
+        }
+    }
+
+    // Ignores the translation part
+    pub fn transform_vector(&self, v: Vec3) -> Vec3 {
+        let m = &self.m;
+        Vec3::new(
+            m[0][0] * v.x + m[0][1] * v.y + m[0][2] * v.z,
+            m[1][0] * v.x + m[1][1] * v.y + m[1][2] * v.z,
+            m[2][0] * v.x + m[2][1] * v.y + m[2][2] * v.z,
+        )
+    }
+}
+
+impl Mul for Matrix4 {
+    type Output = Matrix4;
+
+    fn mul(self, rhs: Matrix4) -> Matrix4 {
+        let mut m = [[0.0; 4]; 4];
+        for (i, row) in m.iter_mut().enumerate() {
+            for (j, value) in row.iter_mut().enumerate() {
+                *value = (0..4).map(|k| self.m[i][k] * rhs.m[k][j]).sum();
+            }
+        }
+        Matrix4 { m }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Transform {
+    pub matrix: Matrix4,
+    pub inverse: Matrix4,
+}
+
+impl Transform {
+    pub fn identity() -> Self {
+        Transform {
+            matrix: Matrix4::identity(),
+            inverse: Matrix4::identity(),
+        }
+    }
+
+    // `None` if the matrix cannot be inverted
+    pub fn from_matrix(matrix: Matrix4) -> Option<Self> {
+        Some(Transform {
+            matrix,
+            inverse: matrix.inverse()?,
+        })
+    }
+
+    pub fn translate(offset: Vec3) -> Self {
+        let mut matrix = Matrix4::identity();
+        let mut inverse = Matrix4::identity();
+        for axis in 0..3 {
+            matrix.m[axis][3] = offset[axis];
+            inverse.m[axis][3] = -offset[axis];
+        }
+        Transform { matrix, inverse }
+    }
+
+    // Non-uniform scale along the axes. Every factor must be non-zero.
+    pub fn scale(factors: Vec3) -> Self {
+        let mut matrix = Matrix4::identity();
+        let mut inverse = Matrix4::identity();
+        for axis in 0..3 {
+            matrix.m[axis][axis] = factors[axis];
+            inverse.m[axis][axis] = 1.0 / factors[axis];
+        }
+        Transform { matrix, inverse }
+    }
+
+    // Counterclockwise rotation by `degrees` about `axis`, looking down the
+    // axis towards the origin
+    pub fn rotate(axis: Vec3, degrees: f64) -> Self {
+        let a = axis.normalize();
+        let (sin, cos) = degrees.to_radians().sin_cos();
+        let t = 1.0 - cos;
+        let matrix = Matrix4::new([
+            [t * a.x * a.x + cos, t * a.x * a.y - sin * a.z, t * a.x * a.z + sin * a.y, 0.0],
+            [t * a.x * a.y + sin * a.z, t * a.y * a.y + cos, t * a.y * a.z - sin * a.x, 0.0],
+            [t * a.x * a.z - sin * a.y, t * a.y * a.z + sin * a.x, t * a.z * a.z + cos, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ]);
+        // Rotations are orthogonal
+        Transform {
+            matrix,
+            inverse: matrix.transpose(),
+        }
+    }
+
+    pub fn rotate_x(degrees: f64) -> Self {
+        Transform::rotate(Vec3::new(1.0, 0.0, 0.0), degrees)
+    }
+
+    pub fn rotate_y(degrees: f64) -> Self {
+        Transform::rotate(Vec3::new(0.0, 1.0, 0.0), degrees)
+    }
+
+    pub fn rotate_z(degrees: f64) -> Self {
+        Transform::rotate(Vec3::new(0.0, 0.0, 1.0), degrees)
+    }
+
+    // `self` followed by `next`
+    pub fn then(&self, next: &Transform) -> Transform {
+        Transform {
+            matrix: next.matrix * self.matrix,
+            inverse: self.inverse * next.inverse,
+        }
+    }
+
+    pub fn inverse(&self) -> Transform {
+        Transform {
+            matrix: self.inverse,
+            inverse: self.matrix,
+        }
+    }
+
+    pub fn is_identity(&self) -> bool {
+        self.matrix == Matrix4::identity()
+    }
+
+    pub fn point(&self, p: Point3) -> Point3 {
+        self.matrix.transform_point(p)
+    }
+
+    pub fn vector(&self, v: Vec3) -> Vec3 {
+        self.matrix.transform_vector(v)
+    }
+
+    // Normals stay perpendicular to the surface under non-uniform scaling
+    // by using the inverse transpose. The result is not normalized.
+    pub fn normal(&self, n: Vec3) -> Vec3 {
+        self.inverse.transpose().transform_vector(n)
+    }
+
+    // Box enclosing the transformed corners of `bounds`
+    pub fn bounds(&self, bounds: &Aabb) -> Aabb {
+        let corners: Vec<Point3> = (0..8)
+            .map(|i| {
+                Point3::new(
+                    if i & 1 == 0 { bounds.min.x } else { bounds.max.x },
+                    if i & 2 == 0 { bounds.min.y } else { bounds.max.y },
+                    if i & 4 == 0 { bounds.min.z } else { bounds.max.z },
+                )
+            })
+            .map(|corner| self.point(corner))
+            .collect();
+        Aabb::from_points(&corners)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scaled_rotated_and_moved() -> Transform {
+        Transform::scale(Vec3::new(2.0, 0.5, 3.0))
+            .then(&Transform::rotate(Vec3::new(1.0, 2.0, 3.0), 37.0))
+            .then(&Transform::translate(Vec3::new(-1.0, 4.0, 2.5)))
+    }
+
+    fn assert_close(a: Vec3, b: Vec3) {
+        assert!((a - b).length() < 1e-9, "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn inverse_undoes_the_transform() {
+        let transform = scaled_rotated_and_moved();
+        let p = Point3::new(0.3, -1.2, 5.0);
+        assert_close(Vec3::from(transform.inverse().point(transform.point(p))), Vec3::from(p));
+        let v = Vec3::new(-2.0, 0.7, 1.1);
+        assert_close(transform.inverse().vector(transform.vector(v)), v);
+
+        // The composed inverse matches a numerical inversion
+        let inverted = transform.matrix.inverse().unwrap();
+        for (row, expected) in transform.inverse.m.iter().zip(&inverted.m) {
+            for (x, y) in row.iter().zip(expected) {
+                assert!((x - y).abs() < 1e-9);
+            }
+        }
+    }
+
+    #[test]
+    fn normals_stay_perpendicular_to_the_surface() {
+        let transform = scaled_rotated_and_moved();
+        // Two directions along a tilted surface and its normal
+        let (u, v) = (Vec3::new(1.0, 1.0, 0.0), Vec3::new(0.0, 1.0, -1.0));
+        let normal = u.cross(&v);
+        let moved = transform.normal(normal);
+        assert!(moved.dot(&transform.vector(u)).abs() < 1e-9);
+        assert!(moved.dot(&transform.vector(v)).abs() < 1e-9);
+        // Still on the outside
+        assert!(moved.dot(&transform.vector(normal)) > 0.0);
+    }
+}
@@ -0,0 +1,399 @@
+// Vectors and points in 3D space
+//
+// `Vec3` is a direction or an offset; `Point3` is a position. The
+// difference of two points is a vector and a point moved by a vector is a
+// point, but points cannot be added together or scaled. Colors have their
+// own type in `color`.
+
+use std::iter::Sum;
+use std::ops::{Add, AddAssign, Div, DivAssign, Index, IndexMut, Mul, MulAssign, Neg, Sub, SubAssign};
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Vec3 {
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+}
+
+impl Vec3 {
+    pub fn new(x: f64, y: f64, z: f64) -> Self {
+        Vec3 { x, y, z }
+    }
+
+    pub fn zero() -> Self {
+        Vec3::new(0.0, 0.0, 0.0)
+    }
+
+    pub fn dot(&self, other: &Vec3) -> f64 {
+        self.x * other.x + self.y * other.y + self.z * other.z
+    }
+
+    pub fn length(&self) -> f64 {
+        self.dot(self).sqrt()
+    }
+
+    pub fn normalize(&self) -> Vec3 {
+        let len = self.length();
+        if len > 0.0 {
+            *self / len
+        } else {
+            *self
+        }
+    }
+
+    pub fn cross(&self, other: &Vec3) -> Vec3 {
+        Vec3::new(
+            self.y * other.z - self.z * other.y,
+            self.z * other.x - self.x * other.z,
+            self.x * other.y - self.y * other.x,
+        )
+    }
+
+    // Mirror image about the plane with unit normal `normal`
+    pub fn reflect(&self, normal: &Vec3) -> Vec3 {
+        *self - *normal * (2.0 * self.dot(normal))
+    }
+
+    // Snell's law for a unit vector hitting a surface with unit normal
+    // `normal` facing against it. `eta` is the ratio of refractive indices
+    // (incident over transmitted). Returns `None` on total internal
+    // reflection.
+    pub fn refract(&self, normal: &Vec3, eta: f64) -> Option<Vec3> {
+        let cos_theta = (-*self).dot(normal).min(1.0);
+        let sin2_theta_t = eta * eta * (1.0 - cos_theta * cos_theta);
+        if sin2_theta_t > 1.0 {
+            return None;
+        }
+        let perpendicular = (*self + *normal * cos_theta) * eta;
+        let parallel = *normal * -(1.0 - sin2_theta_t).sqrt();
+        Some(perpendicular + parallel)
+    }
+
+    // `self` at t = 0, `other` at t = 1
+    pub fn lerp(&self, other: &Vec3, t: f64) -> Vec3 {
+        *self * (1.0 - t) + *other * t
+    }
+
+    // Component-wise minimum
+    pub fn min(&self, other: &Vec3) -> Vec3 {
+        Vec3::new(self.x.min(other.x), self.y.min(other.y), self.z.min(other.z))
+    }
+
+    // Component-wise maximum
+    pub fn max(&self, other: &Vec3) -> Vec3 {
+        Vec3::new(self.x.max(other.x), self.y.max(other.y), self.z.max(other.z))
+    }
+}
+
+impl Add for Vec3 {
+    type Output = Vec3;
+
+    fn add(self, other: Vec3) -> Vec3 {
+        Vec3::new(self.x + other.x, self.y + other.y, self.z + other.z)
+    }
+}
+
+impl Sub for Vec3 {
+    type Output = Vec3;
+
+    fn sub(self, other: Vec3) -> Vec3 {
+        Vec3::new(self.x - other.x, self.y - other.y, self.z - other.z)
+    }
+}
+
+impl Neg for Vec3 {
+    type Output = Vec3;
+
+    fn neg(self) -> Vec3 {
+        Vec3::new(-self.x, -self.y, -self.z)
+    }
+}
+
+impl Mul<f64> for Vec3 {
+    type Output = Vec3;
+
+    fn mul(self, rhs: f64) -> Vec3 {
+        Vec3::new(self.x * rhs, self.y * rhs, self.z * rhs)
+    }
+}
+
+impl Mul<Vec3> for f64 {
+    type Output = Vec3;
+
+    fn mul(self, rhs: Vec3) -> Vec3 {
+        rhs * self
+    }
+}
+
+// Component-wise product
+impl Mul for Vec3 {
+    type Output = Vec3;
+
+    fn mul(self, rhs: Vec3) -> Vec3 {
+        Vec3::new(self.x * rhs.x, self.y * rhs.y, self.z * rhs.z)
+    }
+}
+
+impl Div<f64> for Vec3 {
+    type Output = Vec3;
+
+    fn div(self, rhs: f64) -> Vec3 {
+        Vec3::new(self.x / rhs, self.y / rhs, self.z / rhs)
+    }
+}
+
+// Component-wise quotient
+impl Div for Vec3 {
+    type Output = Vec3;
+
+    fn div(self, rhs: Vec3) -> Vec3 {
+        Vec3::new(self.x / rhs.x, self.y / rhs.y, self.z / rhs.z)
+    }
+}
+
+impl AddAssign for Vec3 {
+    fn add_assign(&mut self, other: Vec3) {
+        *self = *self + other;
+    }
+}
+
+impl SubAssign for Vec3 {
+    fn sub_assign(&mut self, other: Vec3) {
+        *self = *self - other;
+    }
+}
+
+impl MulAssign<f64> for Vec3 {
+    fn mul_assign(&mut self, rhs: f64) {
+        *self = *self * rhs;
+    }
+}
+
+impl MulAssign for Vec3 {
+    fn mul_assign(&mut self, rhs: Vec3) {
+        *self = *self * rhs;
+    }
+}
+
+impl DivAssign<f64> for Vec3 {
+    fn div_assign(&mut self, rhs: f64) {
+        *self = *self / rhs;
+    }
+}
+
+impl DivAssign for Vec3 {
+    fn div_assign(&mut self, rhs: Vec3) {
+        *self = *self / rhs;
+    }
+}
+
+impl Sum for Vec3 {
+    fn sum<I: Iterator<Item = Vec3>>(iter: I) -> Vec3 {
+        iter.fold(Vec3::zero(), Add::add)
+    }
+}
+
+// Components by axis: 0 is x, 1 is y and 2 is z
+impl Index<usize> for Vec3 {
+    type Output = f64;
+
+    fn index(&self, axis: usize) -> &f64 {
+        match axis {
+            0 => &self.x,
+            1 => &self.y,
+            2 => &self.z,
+            _ => panic!("Vec3 axis {} out of range", axis),
+        }
+    }
+}
+
+impl IndexMut<usize> for Vec3 {
+    fn index_mut(&mut self, axis: usize) -> &mut f64 {
+        match axis {
+            0 => &mut self.x,
+            1 => &mut self.y,
+            2 => &mut self.z,
+            _ => panic!("Vec3 axis {} out of range", axis),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Point3 {
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+}
+
+impl Point3 {
+    pub fn new(x: f64, y: f64, z: f64) -> Self {
+        Point3 { x, y, z }
+    }
+
+    pub fn origin() -> Self {
+        Point3::new(0.0, 0.0, 0.0)
+    }
+
+    pub fn distance(&self, other: &Point3) -> f64 {
+        (*self - *other).length()
+    }
+
+    // `self` at t = 0, `other` at t = 1
+    pub fn lerp(&self, other: &Point3, t: f64) -> Point3 {
+        *self + (*other - *self) * t
+    }
+
+    // Component-wise minimum
+    pub fn min(&self, other: &Point3) -> Point3 {
+        Point3::new(self.x.min(other.x), self.y.min(other.y), self.z.min(other.z))
+    }
+
+    // Component-wise maximum
+    pub fn max(&self, other: &Point3) -> Point3 {
+        Point3::new(self.x.max(other.x), self.y.max(other.y), self.z.max(other.z))
+    }
+}
+
+// Position relative to the origin
+impl From<Point3> for Vec3 {
+    fn from(p: Point3) -> Vec3 {
+        Vec3::new(p.x, p.y, p.z)
+    }
+}
+
+// The origin moved by `v`
+impl From<Vec3> for Point3 {
+    fn from(v: Vec3) -> Point3 {
+        Point3::new(v.x, v.y, v.z)
+    }
+}
+
+impl Add<Vec3> for Point3 {
+    type Output = Point3;
+
+    fn add(self, offset: Vec3) -> Point3 {
+        Point3::new(self.x + offset.x, self.y + offset.y, self.z + offset.z)
+    }
+}
+
+impl Sub<Vec3> for Point3 {
+    type Output = Point3;
+
+    fn sub(self, offset: Vec3) -> Point3 {
+        Point3::new(self.x - offset.x, self.y - offset.y, self.z - offset.z)
+    }
+}
+
+// Vector from `other` to `self`
+impl Sub for Point3 {
+    type Output = Vec3;
+
+    fn sub(self, other: Point3) -> Vec3 {
+        Vec3::new(self.x - other.x, self.y - other.y, self.z - other.z)
+    }
+}
+
+impl AddAssign<Vec3> for Point3 {
+    fn add_assign(&mut self, offset: Vec3) {
+        *self = *self + offset;
+    }
+}
+
+impl SubAssign<Vec3> for Point3 {
+    fn sub_assign(&mut self, offset: Vec3) {
+        *self = *self - offset;
+    }
+}
+
+impl Index<usize> for Point3 {
+    type Output = f64;
+
+    fn index(&self, axis: usize) -> &f64 {
+        match axis {
+            0 => &self.x,
+            1 => &self.y,
+            2 => &self.z,
+            _ => panic!("Point3 axis {} out of range", axis),
+        }
+    }
+}
+
+impl IndexMut<usize> for Point3 {
+    fn index_mut(&mut self, axis: usize) -> &mut f64 {
+        match axis {
+            0 => &mut self.x,
+            1 => &mut self.y,
+            2 => &mut self.z,
+            _ => panic!("Point3 axis {} out of range", axis),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn arithmetic_is_component_wise() {
+        let (a, b) = (Vec3::new(1.0, 2.0, 3.0), Vec3::new(4.0, -2.0, 0.5));
+        assert_eq!(a + b, Vec3::new(5.0, 0.0, 3.5));
+        assert_eq!(a - b, Vec3::new(-3.0, 4.0, 2.5));
+        assert_eq!(-a, Vec3::new(-1.0, -2.0, -3.0));
+        assert_eq!(a * 2.0, Vec3::new(2.0, 4.0, 6.0));
+        assert_eq!(2.0 * a, a * 2.0);
+        assert_eq!(a * b, Vec3::new(4.0, -4.0, 1.5));
+        assert_eq!(a / 2.0, Vec3::new(0.5, 1.0, 1.5));
+        assert_eq!(a / b, Vec3::new(0.25, -1.0, 6.0));
+        assert_eq!([a, b].into_iter().sum::<Vec3>(), a + b);
+        assert_eq!((a[0], a[1], a[2]), (1.0, 2.0, 3.0));
+    }
+
+    #[test]
+    fn assignment_matches_the_binary_operators() {
+        let (a, b) = (Vec3::new(1.0, 2.0, 3.0), Vec3::new(4.0, -2.0, 0.5));
+        let mut v = a;
+        v += b;
+        assert_eq!(v, a + b);
+        v -= b;
+        assert_eq!(v, a);
+        v *= 3.0;
+        assert_eq!(v, a * 3.0);
+        v /= 3.0;
+        assert_eq!(v, a);
+        v *= b;
+        assert_eq!(v, a * b);
+        v /= b;
+        assert_eq!(v, a);
+        v[2] = 7.0;
+        assert_eq!(v, Vec3::new(1.0, 2.0, 7.0));
+    }
+
+    #[test]
+    fn points_move_by_vectors() {
+        let (p, q) = (Point3::new(1.0, 1.0, 1.0), Point3::new(4.0, 5.0, 1.0));
+        let offset = q - p;
+        assert_eq!(offset, Vec3::new(3.0, 4.0, 0.0));
+        assert_eq!(p + offset, q);
+        assert_eq!(q - offset, p);
+        assert_eq!(p.distance(&q), 5.0);
+        let mut r = p;
+        r += offset;
+        assert_eq!(r, q);
+        r -= offset;
+        assert_eq!(r, p);
+    }
+
+    #[test]
+    fn products_and_reflection() {
+        let (x, y) = (Vec3::new(1.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0));
+        assert_eq!(x.cross(&y), Vec3::new(0.0, 0.0, 1.0));
+        assert_eq!(x.dot(&y), 0.0);
+        assert_eq!(Vec3::new(3.0, 4.0, 0.0).length(), 5.0);
+        // Bouncing off a floor flips the vertical part only
+        assert_eq!(Vec3::new(1.0, -1.0, 0.0).reflect(&y), Vec3::new(1.0, 1.0, 0.0));
+        // Straight through at normal incidence, and no way out past the
+        // critical angle
+        assert_eq!(Vec3::new(0.0, -1.0, 0.0).refract(&y, 1.5), Some(Vec3::new(0.0, -1.0, 0.0)));
+        assert_eq!(Vec3::new(1.0, -0.1, 0.0).normalize().refract(&y, 1.5), None);
+    }
+}
@@ -0,0 +1,82 @@
+// Renders a scene built in code through the public API
+
+use ray_tracer::environment::Background;
+use ray_tracer::hittable::Hittable;
+use ray_tracer::light::{Falloff, Light, LightKind};
+use ray_tracer::material::Material;
+use ray_tracer::shapes::Sphere;
+use ray_tracer::texture::Texture;
+use ray_tracer::{render, Camera, Color, Point3, Projection, RenderSettings, Scene, Vec3};
+use std::sync::Arc;
+
+// A white sphere in front of the camera, lit from above and behind the
+// camera, on a black background
+fn lit_sphere() -> Scene {
+    let material = Arc::new(Material::Lambertian {
+        albedo: Texture::Solid(Color::white()),
+    });
+    let objects: Vec<Box<dyn Hittable>> = vec![Box::new(Sphere::new(Point3::new(0.0, 0.0, -3.0), 1.0, material))];
+    let light = Light::new(
+        LightKind::Point {
+            position: Point3::new(0.0, 2.0, 1.0),
+        },
+        Color::white(),
+        Falloff::None,
+        1,
+    );
+    Scene::new(objects, vec![light], 0.0, Background::Solid(Color::black()))
+}
+
+#[test]
+fn renders_a_scene_built_in_code() {
+    let settings = RenderSettings {
+        width: 32,
+        height: 24,
+        samples: 4,
+        ..RenderSettings::default()
+    };
+    let aspect_ratio = settings.width as f64 / settings.height as f64;
+    let camera = Camera::new(
+        Point3::origin(),
+        Vec3::new(0.0, 0.0, -1.0),
+        Vec3::new(0.0, 1.0, 0.0),
+        Projection::Perspective { fov: 60.0 },
+        aspect_ratio,
+    );
+
+    let framebuffer = render(&lit_sphere(), &camera, &settings);
+    assert_eq!((framebuffer.width, framebuffer.height), (32, 24));
+    let image = framebuffer.image();
+    assert_eq!(image.pixels.len(), 32 * 24);
+
+    // The middle of the frame sees the lit front of the sphere; the
+    // corners see only the background
+    assert!(framebuffer.get(16, 12).luminance() > 0.0);
+    assert_eq!(framebuffer.get(0, 0), Color::black());
+}
+
+#[test]
+fn same_seed_renders_the_same_image() {
+    let settings = RenderSettings {
+        width: 24,
+        height: 16,
+        samples: 8,
+        seed: 42,
+        ..RenderSettings::default()
+    };
+    let camera = Camera::new(
+        Point3::origin(),
+        Vec3::new(0.0, 0.0, -1.0),
+        Vec3::new(0.0, 1.0, 0.0),
+        Projection::Perspective { fov: 60.0 },
+        1.5,
+    );
+    let scene = lit_sphere();
+
+    let first = render(&scene, &camera, &settings).image();
+    let second = render(&scene, &camera, &settings).image();
+    assert_eq!(first.pixels, second.pixels);
+
+    let reseeded = RenderSettings { seed: 43, ..settings };
+    assert_ne!(render(&scene, &camera, &reseeded).image().pixels, first.pixels);
+}